@@ -1,24 +1,114 @@
-use sfml::window::{Event, Key};
+pub mod keymap;
 
-#[derive(Debug)]
+use sfml::window::Event;
+use sfml::window::joystick::Axis;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use crate::state::TICK;
+use keymap::{Bind, Keymap};
+
+// The most moves a held key can give in one tick
+// More than any board is wide or tall
+const MAX_REPEAT: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     Left,
     Right,
     Down,
+    HardDrop,
     RotateLeft,
     RotateRight,
+    Hold,
     Quit,
+    Rebind,
+    Pause,
+    FastForward, // Play a replay faster while held
+    Step, // Play one tick of a paused replay
+    Up, // Move up in a menu, Down moves down
+    Select, // Pick what's chosen in a menu
+    Close, // The window was closed
     LostFocus,
     GainedFocus,
 }
+impl Action {
+    // The name used in the keymap file
+    pub fn get_name(&self) -> &str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Down => "down",
+            Action::HardDrop => "hard_drop",
+            Action::RotateLeft => "rotate_left",
+            Action::RotateRight => "rotate_right",
+            Action::Hold => "hold",
+            Action::Quit => "quit",
+            Action::Rebind => "rebind",
+            Action::Pause => "pause",
+            Action::FastForward => "fast_forward",
+            Action::Step => "step",
+            Action::Up => "up",
+            Action::Select => "select",
+            Action::Close => "close",
+            Action::LostFocus => "lost_focus",
+            Action::GainedFocus => "gained_focus",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::iter().find(|x| x.get_name() == name)
+    }
+
+    // Every action, in order
+    pub fn iter() -> ActionIter {
+        ActionIter{
+            next: 0,
+        }
+    }
+}
+
+pub struct ActionIter {
+    next: usize,
+}
+impl Iterator for ActionIter {
+    type Item = Action;
+
+    fn next(&mut self) -> Option<Action> {
+        let action = ALL.get(self.next).copied();
+        self.next += 1;
+        action
+    }
+}
+
+// Every action
+const ALL: [Action; 17] = [
+    Action::Left, Action::Right, Action::Down, Action::HardDrop, Action::RotateLeft, Action::RotateRight,
+    Action::Hold, Action::Quit, Action::Rebind, Action::Pause, Action::FastForward, Action::Step,
+    Action::Up, Action::Select, Action::Close, Action::LostFocus, Action::GainedFocus,
+];
+// The actions keys can be bound to, in the order they are rebound
+pub const BINDABLE: [Action; 14] = [
+    Action::Left, Action::Right, Action::Down, Action::HardDrop, Action::RotateLeft, Action::RotateRight,
+    Action::Hold, Action::Quit, Action::Rebind, Action::Pause, Action::FastForward, Action::Step,
+    Action::Up, Action::Select,
+];
 
 pub struct Actions {
     left: bool,
     right: bool,
     down: bool,
+    hard_drop: bool,
     rotate_left: bool,
     rotate_right: bool,
+    hold: bool,
     quit: bool,
+    rebind: bool,
+    pause: bool,
+    fast_forward: bool,
+    step: bool,
+    up: bool,
+    select: bool,
+    close: bool,
     lost_focus: bool,
     gained_focus: bool,
 }
@@ -28,9 +118,18 @@ impl Actions {
             left: false,
             right: false,
             down: false,
+            hard_drop: false,
             rotate_left: false,
             rotate_right: false,
+            hold: false,
             quit: false,
+            rebind: false,
+            pause: false,
+            fast_forward: false,
+            step: false,
+            up: false,
+            select: false,
+            close: false,
             lost_focus: false,
             gained_focus: false,
         }
@@ -41,9 +140,18 @@ impl Actions {
             Action::Left => self.left = b,
             Action::Right => self.right = b,
             Action::Down => self.down = b,
+            Action::HardDrop => self.hard_drop = b,
             Action::RotateLeft => self.rotate_left = b,
             Action::RotateRight => self.rotate_right = b,
+            Action::Hold => self.hold = b,
             Action::Quit => self.quit = b,
+            Action::Rebind => self.rebind = b,
+            Action::Pause => self.pause = b,
+            Action::FastForward => self.fast_forward = b,
+            Action::Step => self.step = b,
+            Action::Up => self.up = b,
+            Action::Select => self.select = b,
+            Action::Close => self.close = b,
             Action::LostFocus => self.lost_focus = b,
             Action::GainedFocus => self.gained_focus = b,
         }
@@ -53,27 +161,81 @@ impl Actions {
             Action::Left => &self.left,
             Action::Right => &self.right,
             Action::Down => &self.down,
+            Action::HardDrop => &self.hard_drop,
             Action::RotateLeft => &self.rotate_left,
             Action::RotateRight => &self.rotate_right,
+            Action::Hold => &self.hold,
             Action::Quit => &self.quit,
+            Action::Rebind => &self.rebind,
+            Action::Pause => &self.pause,
+            Action::FastForward => &self.fast_forward,
+            Action::Step => &self.step,
+            Action::Up => &self.up,
+            Action::Select => &self.select,
+            Action::Close => &self.close,
             Action::LostFocus => &self.lost_focus,
             Action::GainedFocus => &self.gained_focus,
         }
     }
+
+    // The actions that are held
+    pub fn iter(&self) -> ActionsIter<'_> {
+        ActionsIter{
+            actions: self,
+            all: Action::iter(),
+        }
+    }
+}
+
+pub struct ActionsIter<'a> {
+    actions: &'a Actions,
+    all: ActionIter,
+}
+impl Iterator for ActionsIter<'_> {
+    type Item = Action;
+
+    fn next(&mut self) -> Option<Action> {
+        let actions = self.actions;
+        self.all.find(|x| *actions.get(*x))
+    }
+}
+
+// An action being pressed or let go, and when
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionEvent {
+    pub action: Action,
+    pub pressed: bool,
+    pub time: Instant,
 }
 
 pub struct Input {
     window: Option<Event>,
     actions: Actions,
+    keymap: Keymap,
+    axes: Vec<(u32, Axis, bool)>, // The gamepad axes pushed past the deadzone, and which way
+    events: VecDeque<ActionEvent>, // Every press and let go that hasn't been taken yet, oldest first
+    capture: Option<Option<Bind>>, // Waiting for something to be pressed to rebind, and what was once it is
 }
 impl Input {
     pub fn new() -> Input {
         Input {
             window: None,
             actions: Actions::new(),
+            keymap: Keymap::new(),
+            axes: vec![],
+            events: VecDeque::new(),
+            capture: None,
         }
     }
 
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
     pub fn process(&mut self, events: Option<Event>) {
         self.window = events;
         self.match_actions();
@@ -82,25 +244,85 @@ impl Input {
 
     fn match_actions(&mut self) {
         let x = match self.window {
-            Some(Event::KeyPressed {code: c, alt: _, ctrl: _, shift: _, system: _}) => Some((c, true)),
-            Some(Event::KeyReleased {code: c, alt: _, ctrl: _, shift: _, system: _}) => Some((c, false)),
-            Some(Event::Closed) => {self.set_action(Action::Quit, true); None},
+            Some(Event::KeyPressed {code: c, alt: _, ctrl: _, shift: _, system: _}) => Some((Bind::Key(c), true)),
+            Some(Event::KeyReleased {code: c, alt: _, ctrl: _, shift: _, system: _}) => Some((Bind::Key(c), false)),
+            Some(Event::JoystickButtonPressed {joystickid: _, button: b}) => Some((Bind::Button(b), true)),
+            Some(Event::JoystickButtonReleased {joystickid: _, button: b}) => Some((Bind::Button(b), false)),
+            Some(Event::JoystickMoved {joystickid: j, axis: a, position: p}) => {self.move_axis(j, a, self.keymap.get_direction(p)); None},
+            Some(Event::JoystickDisconnected {joystickid: j}) => {self.release_axes(j); None},
+            Some(Event::Closed) => {self.set_action(Action::Close, true); None},
             Some(Event::LostFocus) => {self.set_action(Action::LostFocus, true); self.set_action(Action::GainedFocus, false); None},
             Some(Event::GainedFocus) => {self.set_action(Action::GainedFocus, true); self.set_action(Action::LostFocus, false); None},
             _ => None
         };
-        match x {
-            Some((Key::A, n)) => self.set_action(Action::Left, n),
-            Some((Key::S, n)) => self.set_action(Action::Down, n),
-            Some((Key::D, n)) => self.set_action(Action::Right, n),
-            Some((Key::Q, n)) => self.set_action(Action::RotateLeft, n),
-            Some((Key::E, n)) => self.set_action(Action::RotateRight, n),
-            Some((Key::Escape, n)) => self.set_action(Action::Quit, n),
-            _ => (),
+        if let Some((bind, n)) = x {
+            self.set_bind(bind, n);
+        }
+    }
+
+    fn set_bind(&mut self, bind: Bind, b: bool) {
+        if b && self.capture == Some(None) {
+            self.capture = Some(Some(bind));
+            return
         }
+        for action in self.keymap.get_actions(bind) {
+            self.set_action(action, b);
+        }
+    }
+
+    // Sticks send lots of small moves, only press or let go
+    // when one crosses the deadzone or changes direction
+    fn move_axis(&mut self, joystick: u32, axis: Axis, direction: Option<bool>) {
+        let old = self.axes.iter().position(|x| x.0 == joystick && x.1 == axis);
+        if old.map(|n| self.axes[n].2) == direction {
+            return
+        }
+        if let Some(n) = old {
+            let (_, _, dir) = self.axes.remove(n);
+            self.set_bind(Bind::Axis(axis, dir), false);
+        }
+        if let Some(dir) = direction {
+            self.axes.push((joystick, axis, dir));
+            self.set_bind(Bind::Axis(axis, dir), true);
+        }
+    }
+
+    // Let go of everything a gamepad was pushing when it's unplugged
+    fn release_axes(&mut self, joystick: u32) {
+        let axes: Vec<Axis> = self.axes.iter().filter(|x| x.0 == joystick).map(|x| x.1).collect();
+        for axis in axes {
+            self.move_axis(joystick, axis, None);
+        }
+    }
+
+    // Forget every key being held and every event not taken yet
+    // and stop waiting for something to rebind
+    pub fn clear(&mut self) {
+        self.actions = Actions::new();
+        self.axes.clear();
+        self.events.clear();
+        self.capture = None;
     }
 
+    // Take the next key, button or stick pushed as it is instead of as actions
+    pub fn capture(&mut self) {
+        self.capture = Some(None);
+    }
+
+    // What was pressed since capture was called, if anything has been yet
+    pub fn take_capture(&mut self) -> Option<Bind> {
+        let bind = self.capture.flatten();
+        if bind.is_some() {
+            self.capture = None;
+        }
+        bind
+    }
+
+    // Only a change is an event, holding a second key for the same action isn't
     pub fn set_action(&mut self, a: Action, b: bool) {
+        if *self.actions.get(a) != b {
+            self.events.push_back(ActionEvent{action: a, pressed: b, time: Instant::now()});
+        }
         self.actions.set(a, b);
     }
 
@@ -109,16 +331,134 @@ impl Input {
 
     }
 
-    pub fn get_iter(&self) -> Vec<Action> {
-        let mut vec = vec![];
-        if self.get_action(Action::Left) == &true {vec.push(Action::Left)}
-        if self.get_action(Action::Down) == &true {vec.push(Action::Down)}
-        if self.get_action(Action::Right) == &true {vec.push(Action::Right)}
-        if self.get_action(Action::RotateLeft) == &true {vec.push(Action::RotateLeft)}
-        if self.get_action(Action::RotateRight) == &true {vec.push(Action::RotateRight)}
-        if self.get_action(Action::Quit) == &true {vec.push(Action::Quit)}
-        if self.get_action(Action::LostFocus) == &true {vec.push(Action::LostFocus)}
-        if self.get_action(Action::GainedFocus) == &true {vec.push(Action::GainedFocus)}
-        return vec
+    pub fn get_actions(&self) -> &Actions {
+        &self.actions
+    }
+
+    // Take the events that happened up to a time, oldest first
+    pub fn take_events(&mut self, until: Instant) -> Vec<ActionEvent> {
+        let n = self.events.iter().take_while(|x| x.time <= until).count();
+        self.events.drain(..n).collect()
+    }
+
+    // If there are events that haven't been taken yet
+    pub fn has_events(&self) -> bool {
+        !self.events.is_empty()
+    }
+}
+
+// How held keys repeat
+#[derive(Clone, Copy, Debug)]
+pub struct Timing {
+    pub das: Duration, // How long left or right is held before it repeats
+    pub arr: Duration, // How long between each repeat, 0 goes straight to the wall
+    pub soft_drop: f64, // How many times faster than gravity down drops the piece
+}
+impl Timing {
+    pub fn new() -> Timing {
+        Timing{
+            das: Duration::from_millis(167),
+            arr: Duration::from_millis(33),
+            soft_drop: 20.0,
+        }
+    }
+}
+impl Default for Timing {
+    fn default() -> Timing {
+        Timing::new()
+    }
+}
+
+// Turns presses and the keys being held into moves
+pub struct Repeat {
+    timing: Timing,
+    shift: Option<Action>, // The direction that is being moved in
+    charge: Duration, // How long it has been held
+    left: bool,
+    right: bool,
+    down: bool,
+    drop: f64, // How far the soft drop has got that hasn't been given yet
+}
+impl Repeat {
+    pub fn new(timing: Timing) -> Repeat {
+        Repeat{
+            timing,
+            shift: None,
+            charge: Duration::ZERO,
+            left: false,
+            right: false,
+            down: false,
+            drop: 0.0,
+        }
+    }
+
+    // A press moves straight away, even if it's let go before the tick ends
+    // The last direction pressed wins
+    // Letting go of it goes back to the other one if it's still held
+    pub fn event(&mut self, event: &ActionEvent) -> Option<Action> {
+        match (event.action, event.pressed) {
+            (Action::Left, true) | (Action::Right, true) => {
+                self.shift = Some(event.action);
+                self.charge = Duration::ZERO;
+            },
+            (Action::Down, true) => self.drop = 0.0,
+            _ => (),
+        }
+        match event.action {
+            Action::Left => self.left = event.pressed,
+            Action::Right => self.right = event.pressed,
+            Action::Down => self.down = event.pressed,
+            _ => return None,
+        }
+
+        if !event.pressed && self.shift == Some(event.action) {
+            self.shift = match event.action {
+                Action::Left if self.right => Some(Action::Right),
+                Action::Right if self.left => Some(Action::Left),
+                _ => None,
+            };
+            self.charge = Duration::ZERO;
+        }
+        if event.pressed {
+            Some(event.action)
+        } else {
+            None
+        }
+    }
+
+    // The moves from keys being held for one tick
+    // Gravity is in cells per tick, soft drop is a multiple of it
+    pub fn tick(&mut self, gravity: f64) -> Vec<Action> {
+        let mut actions = vec![];
+
+        if let Some(shift) = self.shift {
+            // Moves are due at das, then every arr after it
+            let timing = self.timing;
+            let due = |held: Duration| -> usize {
+                if held < timing.das {
+                    0
+                } else if timing.arr.is_zero() {
+                    MAX_REPEAT
+                } else {
+                    ((held - timing.das).as_nanos() / timing.arr.as_nanos()) as usize + 1
+                }
+            };
+            let before = due(self.charge);
+            self.charge += TICK;
+            let moves = if timing.arr.is_zero() {due(self.charge)} else {due(self.charge) - before};
+            actions.extend(std::iter::repeat_n(shift, moves.min(MAX_REPEAT)));
+        }
+
+        // Soft drop
+        if self.down {
+            self.drop += gravity * self.timing.soft_drop;
+            let rows = (self.drop as usize).min(MAX_REPEAT);
+            self.drop -= self.drop.floor();
+            actions.extend(std::iter::repeat_n(Action::Down, rows));
+        } else {
+            self.drop = 0.0;
+        }
+
+        actions
     }
-}
\ No newline at end of file
+}
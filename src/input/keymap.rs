@@ -0,0 +1,234 @@
+use sfml::window::{joystick::Axis, Key};
+use std::fs;
+use crate::input::{Action, BINDABLE};
+
+// Every key that can be bound, by the name used in the keymap file
+const KEYS: [Key; 101] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::Escape, Key::LControl, Key::LShift, Key::LAlt, Key::LSystem,
+    Key::RControl, Key::RShift, Key::RAlt, Key::RSystem, Key::Menu,
+    Key::LBracket, Key::RBracket, Key::Semicolon, Key::Comma, Key::Period, Key::Quote, Key::Slash,
+    Key::Backslash, Key::Tilde, Key::Equal, Key::Hyphen, Key::Space, Key::Enter, Key::Backspace, Key::Tab,
+    Key::PageUp, Key::PageDown, Key::End, Key::Home, Key::Insert, Key::Delete,
+    Key::Add, Key::Subtract, Key::Multiply, Key::Divide,
+    Key::Left, Key::Right, Key::Up, Key::Down,
+    Key::Numpad0, Key::Numpad1, Key::Numpad2, Key::Numpad3, Key::Numpad4,
+    Key::Numpad5, Key::Numpad6, Key::Numpad7, Key::Numpad8, Key::Numpad9,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+    Key::F9, Key::F10, Key::F11, Key::F12, Key::F13, Key::F14, Key::F15, Key::Pause,
+];
+// Every gamepad stick and D-pad axis
+const AXES: [Axis; 8] = [Axis::X, Axis::Y, Axis::Z, Axis::R, Axis::U, Axis::V, Axis::PovX, Axis::PovY];
+
+// Something that can be pressed to do an action
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bind {
+    Key(Key),
+    Button(u32), // A gamepad button
+    Axis(Axis, bool), // A gamepad stick or D-pad pushed one way, true is towards positive
+}
+impl Bind {
+    // The name used in the keymap file
+    // Keys are like A or Left, buttons like Button0, axes like X- or PovY+
+    pub fn get_name(&self) -> String {
+        match self {
+            Bind::Key(key) => format!("{:?}", key),
+            Bind::Button(button) => format!("Button{}", button),
+            Bind::Axis(axis, true) => format!("{:?}+", axis),
+            Bind::Axis(axis, false) => format!("{:?}-", axis),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Bind> {
+        if let Some(button) = name.strip_prefix("Button") {
+            return button.parse().ok().map(Bind::Button)
+        }
+        if let Some(axis) = name.strip_suffix('+') {
+            return AXES.iter().find(|x| format!("{:?}", x) == axis).map(|x| Bind::Axis(*x, true))
+        }
+        if let Some(axis) = name.strip_suffix('-') {
+            return AXES.iter().find(|x| format!("{:?}", x) == axis).map(|x| Bind::Axis(*x, false))
+        }
+        KEYS.iter().find(|x| format!("{:?}", x) == name).map(|x| Bind::Key(*x))
+    }
+
+    // If both are keys, both buttons or both axes
+    pub fn is_same_kind(&self, other: Bind) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(&other)
+    }
+}
+
+// Which keys and buttons do which actions
+// One can do more than one action, and an action can have any number of them
+// The file is TOML, each action is set to a list of names, see Bind
+// like left = ["A", "Left", "PovX-"]
+// deadzone is how far a stick has to be pushed before it counts, out of 100
+#[derive(Clone)]
+pub struct Keymap {
+    binds: Vec<(Bind, Action)>,
+    deadzone: f32,
+}
+impl Keymap {
+    // Gamepad buttons are numbered like an Xbox pad, 0 is A and 4 is the left bumper
+    // Up on the D-pad is PovY+
+    // Menus use the same keys as the game, so some do two things
+    pub fn new() -> Keymap {
+        let mut keymap = Keymap{
+            binds: vec![],
+            deadzone: 50.0,
+        };
+        keymap.set_binds(Action::Left, &[Bind::Key(Key::A), Bind::Key(Key::Left), Bind::Axis(Axis::PovX, false), Bind::Axis(Axis::X, false)]);
+        keymap.set_binds(Action::Right, &[Bind::Key(Key::D), Bind::Key(Key::Right), Bind::Axis(Axis::PovX, true), Bind::Axis(Axis::X, true)]);
+        keymap.set_binds(Action::Down, &[Bind::Key(Key::S), Bind::Key(Key::Down), Bind::Axis(Axis::PovY, false), Bind::Axis(Axis::Y, true)]);
+        keymap.set_binds(Action::HardDrop, &[Bind::Key(Key::W), Bind::Key(Key::Space), Bind::Axis(Axis::PovY, true)]);
+        keymap.set_binds(Action::RotateLeft, &[Bind::Key(Key::Q), Bind::Key(Key::Z), Bind::Button(1)]);
+        keymap.set_binds(Action::RotateRight, &[Bind::Key(Key::E), Bind::Key(Key::X), Bind::Key(Key::Up), Bind::Button(0)]);
+        keymap.set_binds(Action::Hold, &[Bind::Key(Key::LShift), Bind::Key(Key::C), Bind::Button(4), Bind::Button(5)]);
+        keymap.set_binds(Action::Quit, &[Bind::Key(Key::Escape), Bind::Key(Key::Backspace), Bind::Button(6)]);
+        keymap.set_binds(Action::Rebind, &[Bind::Key(Key::F1)]);
+        keymap.set_binds(Action::Pause, &[Bind::Key(Key::P), Bind::Button(7)]);
+        keymap.set_binds(Action::FastForward, &[Bind::Key(Key::F)]);
+        keymap.set_binds(Action::Step, &[Bind::Key(Key::Period)]);
+        keymap.set_binds(Action::Up, &[Bind::Key(Key::W), Bind::Key(Key::Up), Bind::Axis(Axis::PovY, true), Bind::Axis(Axis::Y, false)]);
+        keymap.set_binds(Action::Select, &[Bind::Key(Key::Enter), Bind::Key(Key::Space), Bind::Button(0)]);
+        keymap
+    }
+
+    // Actions missing from the file keep their default keys
+    pub fn load(path: &str) -> Result<Keymap, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Keymap::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Keymap, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string().lines().next().unwrap_or("").to_string())?;
+        let mut keymap = Keymap::new();
+
+        for (name, value) in table {
+            if name == "deadzone" {
+                let deadzone = value.as_float().or_else(|| value.as_integer().map(|x| x as f64))
+                    .filter(|x| (0.0..100.0).contains(x))
+                    .ok_or_else(|| "deadzone has to be a number from 0 to 100".to_string())?;
+                keymap.deadzone = deadzone as f32;
+                continue
+            }
+
+            let action = Action::from_name(&name)
+                .filter(|x| BINDABLE.contains(x))
+                .ok_or_else(|| format!("{} isn't an action", name))?;
+            let list = value.as_array().ok_or_else(|| format!("{} has to be a list of keys", name))?;
+
+            let mut binds = vec![];
+            for bind in list {
+                let bind = bind.as_str().ok_or_else(|| format!("{} has to be a list of keys", name))?;
+                binds.push(Bind::from_name(bind).ok_or_else(|| format!("{} isn't a key or button", bind))?);
+            }
+            keymap.set_binds(action, &binds);
+        }
+        Ok(keymap)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut table = toml::Table::new();
+        table.insert("deadzone".to_string(), toml::Value::from(self.deadzone as f64));
+        for action in BINDABLE {
+            let binds = self.get_binds(action).into_iter().map(|bind| toml::Value::from(bind.get_name())).collect();
+            table.insert(action.get_name().to_string(), toml::Value::Array(binds));
+        }
+        let text = toml::to_string(&table).map_err(|e| format!("{}: {}", path, e))?;
+        fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn get_deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    // Which way a stick at position is pushed, None if it's in the deadzone
+    pub fn get_direction(&self, position: f32) -> Option<bool> {
+        if position > self.deadzone {
+            Some(true)
+        } else if position < -self.deadzone {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // What a key or button does
+    pub fn get_actions(&self, bind: Bind) -> Vec<Action> {
+        self.binds.iter().filter(|x| x.0 == bind).map(|x| x.1).collect()
+    }
+
+    pub fn get_binds(&self, action: Action) -> Vec<Bind> {
+        self.binds.iter().filter(|x| x.1 == action).map(|x| x.0).collect()
+    }
+
+    // Bind something to an action in place of the ones of the same kind
+    // so binding a button keeps the keys, and binding a key keeps the buttons
+    pub fn rebind(&mut self, action: Action, bind: Bind) {
+        self.binds.retain(|x| x.1 != action || !x.0.is_same_kind(bind));
+        self.binds.push((bind, action));
+    }
+
+    // Replace the keys and buttons for an action
+    pub fn set_binds(&mut self, action: Action, binds: &[Bind]) {
+        self.binds.retain(|x| x.1 != action);
+        for bind in binds {
+            self.binds.push((*bind, action));
+        }
+    }
+}
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_read_back() {
+        for bind in [Bind::Key(Key::LShift), Bind::Button(7), Bind::Axis(Axis::PovY, true), Bind::Axis(Axis::X, false)] {
+            assert_eq!(Bind::from_name(&bind.get_name()), Some(bind));
+        }
+        assert_eq!(Bind::from_name("Nothing"), None);
+        assert_eq!(Bind::from_name("ButtonA"), None);
+    }
+
+    #[test]
+    fn files_replace_only_the_actions_given() {
+        let keymap = Keymap::parse("deadzone = 20\nleft = [\"J\", \"Button2\"]\n").unwrap();
+        assert_eq!(keymap.get_deadzone(), 20.0);
+        assert_eq!(keymap.get_binds(Action::Left), vec![Bind::Key(Key::J), Bind::Button(2)]);
+        assert_eq!(keymap.get_binds(Action::Right), Keymap::new().get_binds(Action::Right));
+        assert_eq!(keymap.get_actions(Bind::Key(Key::A)), vec![]);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        for text in ["left = [", "jump = [\"A\"]", "lost_focus = [\"A\"]", "left = \"A\"", "left = [\"Nothing\"]", "left = [1]", "deadzone = 100"] {
+            assert!(Keymap::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn rebinding_keeps_other_kinds() {
+        let mut keymap = Keymap::new();
+        keymap.rebind(Action::Hold, Bind::Key(Key::V));
+        assert_eq!(keymap.get_binds(Action::Hold), vec![Bind::Button(4), Bind::Button(5), Bind::Key(Key::V)]);
+        keymap.rebind(Action::Hold, Bind::Button(3));
+        assert_eq!(keymap.get_binds(Action::Hold), vec![Bind::Key(Key::V), Bind::Button(3)]);
+    }
+
+    #[test]
+    fn deadzone() {
+        let keymap = Keymap::new();
+        assert_eq!(keymap.get_direction(80.0), Some(true));
+        assert_eq!(keymap.get_direction(-80.0), Some(false));
+        assert_eq!(keymap.get_direction(30.0), None);
+    }
+}
@@ -1,25 +1,56 @@
-mod tetris;
-mod input;
+pub mod tetris;
+pub mod input;
+pub mod state;
+pub mod replay;
+pub mod scores;
+pub mod screen;
 
-use crate::tetris::piece;
 use crate::input::Action;
-
-use std::time::{Instant, Duration};
+use crate::input::keymap::Keymap;
+use crate::state::{GameState, Rules, TICK};
+use crate::tetris::set::PieceSet;
+use crate::replay::Replay;
+use crate::scores::{Score, Scores};
+use crate::screen::{Change, Screen};
+use crate::screen::playing::Playing;
+use crate::screen::title::Title;
+use crate::screen::keys::Keys;
+
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 use rand::Rng;
 
 // SFML
 use sfml::window::{Style, ContextSettings};
 use sfml::graphics::*;
 use sfml::system::{Vector2f, Vector2u};
+use sfml::SfBox;
+
+// Where the keys are read from and saved to
+const KEYMAP: &str = "keys.toml";
+// Where every game is saved so it can be watched again
+const REPLAYS: &str = "replays";
+// The pieces played with unless others are picked
+const PIECES: &str = "assets/pieces.txt";
+// What the window is called
+const TITLE: &str = "Test";
 
 pub struct Game {
-    tickrate: Duration, // How many times things are checked a second
-    low_tickrate: Duration, // What's the lowest speed the game can run at
     maxfps: u64,
     window_geometry: (u32, u32),
     window: RenderWindow,
     input: input::Input,
-    score: u64,
+    seed: Option<u64>, // Play every game with this seed instead of a random one
+    rules: Rules,
+    timing: input::Timing,
+    keymap: String, // The file keys are saved to when they are rebound
+    pieces: Rc<PieceSet>,
+    pieces_path: String, // Where the pieces were loaded from, kept in replays
+    playback: Option<Replay>, // The replay being watched instead of playing
+    name: String, // Who is playing, for the high scores
+    scores: Scores,
 }
 impl Game {
     pub fn new() -> Game {
@@ -28,7 +59,7 @@ impl Game {
         context_settings.antialiasing_level = 0;
     let mut window = RenderWindow::new(
         (480, 480),
-        "Test",
+        TITLE,
         Style::DEFAULT,
         &context_settings);
 
@@ -36,6 +67,7 @@ impl Game {
         let geometry = (480, 480);
 
         window.set_framerate_limit(maxfps);
+        // Held keys are repeated by input::Repeat instead
         window.set_key_repeat_enabled(false);
         window.clear(Color::rgb(250, 250, 250));
 
@@ -47,48 +79,109 @@ impl Game {
             view.set_viewport(&FloatRect{left: 0.0, top: 0.0, width: 1.0, height: 1.0});
         window.set_view(&view);
 
-        Game{
-            tickrate: Duration::from_millis(1000),
-            low_tickrate: Duration::from_millis(150),
+        let mut game = Game{
             maxfps: 30,
             window_geometry: geometry,
             window: window,
             input: input::Input::new(),
-            score: 0,
+            seed: None,
+            rules: Rules::new(),
+            timing: input::Timing::new(),
+            keymap: KEYMAP.to_string(),
+            pieces: Rc::new(PieceSet::load(PIECES).expect("Cannot load pieces")),
+            pieces_path: PIECES.to_string(),
+            playback: None,
+            name: std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "Player".to_string()),
+            scores: Scores::load(Scores::default_path()),
+        };
+        // A broken keymap shouldn't stop the game, the default keys still work
+        if let Err(e) = game.set_keymap(KEYMAP) {
+            eprintln!("{}", e);
         }
+        game
     }
 
-    pub fn get_score(&self) -> u64 {
-        self.score
+    pub fn set_geometry(&mut self, geometry: (u32, u32)) {
+        self.window_geometry = geometry;
+        self.window.set_size(Vector2u::from(geometry));
     }
-    pub fn set_score(&mut self, value: u64) {
-        if value > u64::MAX {
-            self.score = u64::MAX
-        } else {
-            self.score = value;
-        }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
     }
 
-    pub fn get_tickrate(&self) -> Duration {
-        self.tickrate
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
     }
-    pub fn set_tickrate(&mut self, duration: Duration) {
-        if duration < self.low_tickrate {
-            self.tickrate = self.low_tickrate
+
+    pub fn set_timing(&mut self, timing: input::Timing) {
+        self.timing = timing;
+    }
+
+    // Use the keys in a keymap file, rebinding keys saves to it
+    // If there's no file yet the default keys are used
+    pub fn set_keymap(&mut self, path: &str) -> Result<(), String> {
+        self.keymap = path.to_string();
+        let keymap = if Path::new(path).exists() {
+            Keymap::load(path)?
         } else {
-            self.tickrate = duration
+            Keymap::new()
+        };
+        self.input.set_keymap(keymap);
+        Ok(())
+    }
+
+    pub fn set_pieces(&mut self, path: &str) -> Result<(), String> {
+        self.pieces = Rc::new(PieceSet::load(path)?);
+        self.pieces_path = path.to_string();
+        Ok(())
+    }
+
+    // Watch a replay instead of playing
+    // It brings its own seed, rules and pieces
+    pub fn set_replay(&mut self, path: &str) -> Result<(), String> {
+        let replay = Replay::load(path)?;
+        self.set_pieces(replay.get_pieces())?;
+        if self.pieces.get_hash() != replay.get_pieces_hash() {
+            return Err(format!("{}: {} has changed since the replay was recorded", path, replay.get_pieces()))
         }
+        self.seed = Some(replay.get_seed());
+        self.rules = replay.get_rules();
+        self.playback = Some(replay);
+        Ok(())
     }
 
-    pub fn set_geometry(&mut self, geometry: (u32, u32)) {
-        self.window_geometry = geometry;
-        self.window.set_size(Vector2u::from(geometry));
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
     }
 
-    fn pause(&mut self) {
-        loop {
-            self.process_inputs();
-            if *self.input.get_action(Action::GainedFocus) {break}
+    pub fn get_scores(&self) -> &Scores {
+        &self.scores
+    }
+
+    // Keep the score of a game that ended and show where it placed
+    fn save_score(&mut self, state: &GameState) -> Option<usize> {
+        let score = Score::new(
+            &self.name,
+            state.get_score(),
+            state.get_lines(),
+            state.get_level(),
+            TICK * state.get_ticks() as u32,
+            &self.rules.get_mode());
+        let place = self.scores.add(score);
+        if let Err(e) = self.scores.save() {
+            eprintln!("{}", e);
+        }
+        place
+    }
+
+    fn save_replay(&self, replay: &Replay) {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+        let path = format!("{}/{}-{}.replay", REPLAYS, time, replay.get_seed());
+        let saved = fs::create_dir_all(REPLAYS).map_err(|e| format!("{}: {}", REPLAYS, e))
+            .and_then(|_| replay.save(&path));
+        if let Err(e) = saved {
+            eprintln!("{}", e);
         }
     }
 
@@ -100,182 +193,169 @@ impl Game {
         }
     }
 
-    fn reset(&mut self) -> tetris::Tetris {
-        println!("{}", self.get_score());
-        self.set_score(0);
-        tetris::Tetris::new()
+    fn new_state(&self) -> GameState {
+        let seed = match self.seed {
+            Some(seed) => seed,
+            None => rand::thread_rng().gen(),
+        };
+        GameState::new(seed, self.rules, self.pieces.clone())
     }
 
     // The actual game loop
+    // Every screen open is on the stack, the one on top is the one being used
     pub fn game_loop(&mut self) {
-        // Textures
-        // Background
-        let mut background = Texture::from_file("assets/background.png").expect("Cannot load texture");
-        background.set_smooth(false);
-
-        // Bits
-        let mut grey = Texture::from_file("assets/dead.png").expect("Cannot load texture");
-        grey.set_smooth(false);
-        let mut red = Texture::from_file("assets/red.png").expect("Cannot load texture");
-        red.set_smooth(false);
-        let mut green = Texture::from_file("assets/green.png").expect("Cannot load texture");
-        green.set_smooth(false);
-        let mut blue = Texture::from_file("assets/blue.png").expect("Cannot load texture");
-        blue.set_smooth(false);
-
-        let bits_list = [&red, &green, &blue];
-
-
-        // Objects
-        let mut background = RectangleShape::with_texture(&background);
-        background.set_size(Vector2f::new(480.0, 480.0));
-
-        let mut bit = RectangleShape::with_texture(&blue);
-        bit.set_size(Vector2f::new(24.0, 24.0));
-
-        // Initialize the background
-        self.window.draw(&background);
+        let assets = Assets::load();
+        let mut screens: Vec<Box<dyn Screen>> = vec![];
+        match self.playback {
+            Some(_) => screens.push(Box::new(Playing::new(self))),
+            None => screens.push(Box::new(Title::new())),
+        }
 
         // Timing
-        let mut tick = Instant::now();
         let mut fpscap = Instant::now();
-        let mut key_count = 0;
+        let mut clock = Instant::now();
+        let mut lag = Duration::ZERO; // Time the game hasn't caught up to yet
 
-        // Game setup
-        let mut tetris = tetris::Tetris::new();
-        let mut piece = piece::Piece::random(piece::Pos(-2,3));
-        let mut next_piece = piece::Piece::random(piece::Pos(3,13));
+        // Draw the first screen before anything can wait, or the window stays blank until an event
+        self.window.clear(Color::rgb(0,0,0));
+        screens.last().expect("No screens").draw(&mut self.window, &assets);
+        self.window.display();
 
         'main: loop {
-            // Clear everything from display
-            self.window.clear(Color::rgb(0,0,0));
-
+            // Sleep until something happens if the screen won't change until then
+            // The time spent waiting doesn't count
+            if screens.last().expect("No screens").is_waiting() && !self.input.has_events() {
+                let event = self.window.wait_event();
+                self.input.process(event);
+                lag = TICK;
+                clock = Instant::now();
+            }
             self.process_inputs();
 
-            // Process events
-            // for x in self.window.poll_event() {
-            //     match x {
-            //         Event::Closed => break 'main,
-            //         Event::Resized {width: w, height: h} => self.set_geometry((w, h)),
-            //         Event::LostFocus => self.pause(),
-            //         Event::KeyPressed {code: c, ctrl: _, alt: _, shift: _, system: _} => key = Some(c),
-            //         Event::KeyReleased {code: _, ctrl: _, alt: _, shift: _, system: _} => key = None,
-            //         _ => (),
-            //     };
-            // }
-
-            // Check if piece is dead
-            if piece.is_alive() == false {
-                if piece.apply_to_grid(&mut tetris) == false {
-                    // Game has been lost
-                    tetris = self.reset();
+            // Tick once for every tick that has passed
+            // Don't try to catch up on more than a few, like after the window was dragged
+            lag = (lag + clock.elapsed()).min(TICK * 8);
+            clock = Instant::now();
+            while lag >= TICK {
+                lag -= TICK;
+
+                // Give the screen what was pressed and let go up to the end of this tick, in order
+                for event in self.input.take_events(clock - lag) {
+                    if event.pressed && event.action == Action::Close {
+                        break 'main
+                    }
+                    // Rebinding can be opened over any screen
+                    let top = screens.last_mut().expect("No screens");
+                    let change = if event.pressed && event.action == Action::Rebind && !top.is_rebinding() {
+                        Change::Push(Box::new(Keys::new(self)))
+                    } else {
+                        top.event(self, &event)
+                    };
+                    if !self.change(&mut screens, change) {
+                        break 'main
+                    }
+                }
+                let change = screens.last_mut().expect("No screens").tick(self);
+                if !self.change(&mut screens, change) {
+                    break 'main
                 }
-                piece = next_piece;
-                piece.set_pos(tetris::piece::Pos(-2, 3));
-                next_piece = piece::Piece::random(piece::Pos(3,13));
-
-                // Set the color
-                let x = match rand::thread_rng().gen_range(0..bits_list.len()) {
-                    n => bits_list[n]
-                };
-                bit.set_texture(&x, false);
-            }
-            
-            // Check if there are full lines
-            match tetris.check_lines() {
-                0 => (),
-                n => {self.set_score(self.get_score() + (n * n) as u64);
-                    self.set_tickrate(self.get_tickrate() - Duration::from_millis(n as u64 * 25));},
             }
 
-            // Execute on tick
-            if tick.elapsed() >= self.get_tickrate() {
-                // Reset the clock
-                tick = Instant::now();
-                
-                // Timed logic
-                piece.r#move(piece::Dir::Down, &tetris)
-            }
-            
-            
-            
-            if fpscap.elapsed() >= Duration::from_millis(1000 / self.maxfps) {
+            // A screen that's about to wait has to be drawn first
+            let top = screens.last().expect("No screens");
+            if top.is_waiting() || fpscap.elapsed() >= Duration::from_millis(1000 / self.maxfps) {
                 // Reset the clock
                 fpscap = Instant::now();
-                
-                // Process keys
-                // Limited keys
-                if key_count == 0 {
-                    for action in self.input.get_iter() {
-                        match action {
-                            Action::Left  => piece.r#move(tetris::piece::Dir::Left, &tetris),
-                            Action::Right => piece.r#move(tetris::piece::Dir::Right, &tetris),
-                            _ => ()
-                        }
-                    }
-                }
-                //Process keys
-                // Unlimited keys
-                for action in self.input.get_iter() {
-                    match action {
-                        Action::RotateLeft => {piece.rotate(tetris::piece::Rotate::Left, &tetris); self.input.set_action(Action::RotateLeft, false)},
-                        Action::RotateRight => {piece.rotate(tetris::piece::Rotate::Right, &tetris); self.input.set_action(Action::RotateRight, false)},
-                        Action::Down => piece.r#move(tetris::piece::Dir::Down, &tetris),
-                        Action::Quit => break 'main,
-                        Action::LostFocus => self.pause(),
-                        _ => ()
-                    }
-                }
 
-                // Make the limit feel good
-                if *self.input.get_action(Action::Left) != false || *self.input.get_action(Action::Right) != false {
-                    key_count += 1;
-                } else {
-                    key_count = 0;
-                }
-                if key_count > 2 {
-                    key_count = 0;
-                }
+                // Clear everything from display
+                self.window.clear(Color::rgb(0,0,0));
 
-                // Draw the background
-                self.window.draw(&background);
-
-                // Start drawing everything
-                let grid = tetris.return_grid();
-    
-
-                // Draw all the bits
-                // Set the color
-                let old_texture = bit.texture().unwrap();
-                bit.set_texture(&grey, false);
-                for y in (0..grid.len()).rev() {
-                    for x in 0..grid.len() {
-                        if *grid.get(y).unwrap_or(&vec![0]).get(x).unwrap_or(&0) == 1 {
-                            bit.set_position(((x * 24) as f32, (y * 24) as f32));
-                            self.window.draw(&bit); 
-                        }
-                    }
-                }
-                bit.set_texture(&old_texture, false);
+                top.draw(&mut self.window, &assets);
 
-                for piece_bit in piece.get_bits_pos() {
-                    if !(piece_bit.0 < 0 || piece_bit.1 < 0) {
-                        bit.set_position(((piece_bit.1 as f32) * 24.0, (piece_bit.0 as f32) * 24.0));
-                        self.window.draw(&bit); 
-                    }
-                }
-                for piece_bit in next_piece.get_bits_pos() {
-                    if !(piece_bit.0 < 0 || piece_bit.1 < 0) {
-                        bit.set_position(((piece_bit.1 as f32) * 24.0, (piece_bit.0 as f32) * 24.0));
-                        self.window.draw(&bit); 
-                    }
-                }
-        
                 self.window.display();
             }
         }
 
-        println!("{}", self.get_score());
+        self.change(&mut screens, Change::Quit);
         self.window.close();
     }
-}
\ No newline at end of file
+
+    // Do what a screen asked for
+    // Returns false once there are no screens left
+    fn change(&mut self, screens: &mut Vec<Box<dyn Screen>>, change: Change) -> bool {
+        let close = |game: &mut Game, screens: &mut Vec<Box<dyn Screen>>| {
+            if let Some(mut screen) = screens.pop() {
+                screen.close(game);
+            }
+        };
+        match change {
+            Change::None => return true,
+            Change::Push(screen) => screens.push(screen),
+            Change::Pop => close(self, screens),
+            Change::Replace(screen) => {
+                close(self, screens);
+                screens.push(screen);
+            },
+            Change::ReplaceUnder(screen) => {
+                close(self, screens);
+                close(self, screens);
+                screens.push(screen);
+            },
+            Change::Home => while screens.len() > 1 {
+                close(self, screens);
+            },
+            Change::Quit => while !screens.is_empty() {
+                close(self, screens);
+            },
+        }
+        // Keys held on the screen that closed were never let go on this one
+        // and the screen opened only sees what happens from now on
+        self.input.clear();
+        match screens.last_mut() {
+            Some(screen) => {screen.resume(self); true},
+            None => false,
+        }
+    }
+}
+
+// The textures and font everything is drawn with
+pub struct Assets {
+    background: SfBox<Texture>,
+    grey: SfBox<Texture>,
+    bits: [SfBox<Texture>; 3], // One for each color a piece can be
+    font: SfBox<Font>,
+}
+impl Assets {
+    pub fn load() -> Assets {
+        let load = |path: &str| {
+            let mut texture = Texture::from_file(path).expect("Cannot load texture");
+            texture.set_smooth(false);
+            texture
+        };
+        Assets{
+            background: load("assets/background.png"),
+            grey: load("assets/dead.png"),
+            bits: [load("assets/red.png"), load("assets/green.png"), load("assets/blue.png")],
+            font: Font::from_file("assets/DejaVuSansMono.ttf").expect("Cannot load font"),
+        }
+    }
+
+    // The background, filling the window
+    pub fn get_background(&self) -> RectangleShape<'_> {
+        let mut background = RectangleShape::with_texture(&self.background);
+        background.set_size(Vector2f::new(480.0, 480.0));
+        background
+    }
+
+    pub fn get_grey(&self) -> &Texture {
+        &self.grey
+    }
+
+    pub fn get_bit(&self, color: usize) -> &Texture {
+        &self.bits[color]
+    }
+
+    pub fn get_font(&self) -> &Font {
+        &self.font
+    }
+}
@@ -1,6 +1,112 @@
 use tetris::Game;
+use tetris::scores;
+use tetris::input::Timing;
+use tetris::state::Rules;
+use tetris::tetris::randomizer::Randomizers;
+use tetris::tetris::scoring::ScoringRules;
+use std::time::Duration;
 
 fn main() {
     let mut game = Game::new();
+    let mut rules = Rules::new();
+    let mut timing = Timing::new();
+    let mut replay = None;
+
+    // Arguments
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let seed = args.next().and_then(|x| x.parse().ok()).expect("--seed needs a number");
+                game.set_seed(Some(seed));
+            },
+            "--randomizer" => {
+                let randomizer = args.next().and_then(|x| Randomizers::from_name(&x))
+                    .expect("--randomizer needs one of uniform, bag or history");
+                rules.randomizer = randomizer;
+            },
+            "--scoring" => {
+                let scoring = args.next().and_then(|x| ScoringRules::from_name(&x))
+                    .expect("--scoring needs one of guideline or classic");
+                rules.scoring = scoring;
+            },
+            "--previews" => {
+                let previews = args.next().and_then(|x| x.parse().ok()).filter(|x| (1..=6).contains(x)).expect("--previews needs a number from 1 to 6");
+                rules.previews = previews;
+            },
+            "--level" => {
+                let level = args.next().and_then(|x| x.parse().ok()).expect("--level needs a number");
+                rules.start_level = level;
+            },
+            "--das" => {
+                let das = args.next().and_then(|x| x.parse().ok()).expect("--das needs a number of milliseconds");
+                timing.das = Duration::from_millis(das);
+            },
+            "--arr" => {
+                let arr = args.next().and_then(|x| x.parse().ok()).expect("--arr needs a number of milliseconds");
+                timing.arr = Duration::from_millis(arr);
+            },
+            "--soft-drop" => {
+                let factor = args.next().and_then(|x| x.parse().ok()).filter(|x: &f64| *x > 0.0)
+                    .expect("--soft-drop needs how many times faster than gravity");
+                timing.soft_drop = factor;
+            },
+            "--keys" => {
+                let path = args.next().expect("--keys needs a file");
+                if let Err(e) = game.set_keymap(&path) {
+                    eprintln!("{}", e);
+                    std::process::exit(1)
+                }
+            },
+            "--pieces" => {
+                let path = args.next().expect("--pieces needs a file");
+                if let Err(e) = game.set_pieces(&path) {
+                    eprintln!("{}", e);
+                    std::process::exit(1)
+                }
+            },
+            "--name" => {
+                let name = args.next().expect("--name needs a name");
+                game.set_name(&name);
+            },
+            "--scores" => {
+                print!("{}", game.get_scores().table(scores::TOP, None));
+                return
+            },
+            "--replay" => {
+                let path = args.next().expect("--replay needs a file");
+                replay = Some(path);
+            },
+            "--board" => {
+                // Columns, visible rows and hidden rows, like 10x20+20
+                let board = args.next().expect("--board needs a size like 10x20+20");
+                let (size, buffer) = board.split_once('+').unwrap_or((&board, "20"));
+                let (width, height) = size.split_once('x').expect("--board needs a size like 10x20+20");
+                rules.width = width.parse().expect("--board needs a size like 10x20+20");
+                rules.height = height.parse().expect("--board needs a size like 10x20+20");
+                rules.buffer = buffer.parse().expect("--board needs a size like 10x20+20");
+                check(&rules);
+            },
+            _ => eprintln!("Unknown argument: {}", arg),
+        }
+    }
+    game.set_rules(rules);
+    game.set_timing(timing);
+    // A replay has its own rules, so it goes after everything else
+    if let Some(path) = replay {
+        if let Err(e) = game.set_replay(&path) {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    }
+
     game.game_loop()
-}
\ No newline at end of file
+}
+
+// Stop if the rules given can't be played with
+fn check(rules: &Rules) {
+    if let Err(e) = rules.check() {
+        eprintln!("{}", e);
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs;
+use std::time::Duration;
+use crate::input::Action;
+use crate::state::{Rules, RULES_VERSION};
+use crate::tetris::randomizer::Randomizers;
+use crate::tetris::scoring::ScoringRules;
+
+// Which version of the file this writes
+const FORMAT: u64 = 1;
+
+// The most times an action can be given in a row on one tick
+const MAX_COUNT: usize = 1000;
+
+// The actions a game can be given, and the letter each is saved as
+const CODES: [(Action, char); 7] = [
+    (Action::Left, 'L'),
+    (Action::Right, 'R'),
+    (Action::Down, 'D'),
+    (Action::HardDrop, 'H'),
+    (Action::RotateLeft, 'Q'),
+    (Action::RotateRight, 'E'),
+    (Action::Hold, 'C'),
+];
+
+// Everything needed to play a game again
+// The same seed, rules and pieces given the same actions on the same ticks
+// always play out the same, as long as the rules version hasn't changed
+//
+// The file is text, a line for each setting, then a line for each
+// tick something happened on, the tick then a letter for each action
+// An action given more than once in a row has how many times after it
+//   replay 1
+//   rules 2
+//   seed 1234
+//   pieces assets/pieces.txt
+//   pieces_hash 60c6edd2d3299145
+//   ...
+//   120 L2Q
+//   121 R60
+//   end 4000
+pub struct Replay {
+    seed: u64,
+    rules: Rules,
+    pieces: String, // The pieces file the game was played with
+    pieces_hash: u64, // What the pieces file had in it, see PieceSet::get_hash
+    inputs: Vec<(u64, Vec<Action>)>, // Only the ticks something happened on, in order
+    end: u64, // How many ticks the game lasted
+}
+impl Replay {
+    pub fn new(seed: u64, rules: Rules, pieces: &str, pieces_hash: u64) -> Replay {
+        Replay{
+            seed,
+            rules,
+            pieces: pieces.to_string(),
+            pieces_hash,
+            inputs: vec![],
+            end: 0,
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn get_pieces(&self) -> &str {
+        &self.pieces
+    }
+
+    pub fn get_pieces_hash(&self) -> u64 {
+        self.pieces_hash
+    }
+
+    pub fn get_end(&self) -> u64 {
+        self.end
+    }
+
+    // Keep the actions given to the game on a tick
+    pub fn record(&mut self, tick: u64, actions: &[Action]) {
+        let actions: Vec<Action> = actions.iter().copied().filter(|x| code(*x).is_some()).collect();
+        if !actions.is_empty() {
+            self.inputs.push((tick, actions));
+        }
+    }
+
+    // The game is over after this many ticks
+    pub fn finish(&mut self, ticks: u64) {
+        self.end = ticks;
+    }
+
+    // The actions that were given on a tick
+    pub fn get_actions(&self, tick: u64) -> &[Action] {
+        match self.inputs.binary_search_by_key(&tick, |x| x.0) {
+            Ok(n) => &self.inputs[n].1,
+            Err(_) => &[],
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let rules = self.rules;
+        let mut text = String::new();
+        text += &format!("replay {}\n", FORMAT);
+        text += &format!("rules {}\n", RULES_VERSION);
+        text += &format!("seed {}\n", self.seed);
+        text += &format!("pieces {}\n", self.pieces);
+        text += &format!("pieces_hash {:016x}\n", self.pieces_hash);
+        text += &format!("randomizer {}\n", rules.randomizer.get_name());
+        text += &format!("scoring {}\n", rules.scoring.get_name());
+        text += &format!("previews {}\n", rules.previews);
+        text += &format!("lock_delay {}\n", rules.lock_delay.as_millis());
+        text += &format!("lock_resets {}\n", rules.lock_resets);
+        text += &format!("level {}\n", rules.start_level);
+        text += &format!("level_lines {}\n", rules.level_lines);
+        text += &format!("board {}x{}+{}\n", rules.width, rules.height, rules.buffer);
+        for (tick, actions) in &self.inputs {
+            text += &format!("{} {}\n", tick, codes(actions));
+        }
+        text += &format!("end {}\n", self.end);
+        fs::write(path, text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Replay::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut replay = Replay::new(0, Rules::new(), "", 0);
+        let mut format = None;
+        let mut version = None;
+        let mut pieces_hash = None;
+
+        for (n, line) in text.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {}", n + 1, e);
+            let line = line.trim();
+            if line.is_empty() {
+                continue
+            }
+            // The value is the rest of the line, a pieces file can have spaces in its name
+            let (first, arg) = line.split_once(char::is_whitespace)
+                .map(|(first, arg)| (first, arg.trim()))
+                .ok_or_else(|| error("Every line needs a value"))?;
+            let number = || arg.parse::<u64>().map_err(|_| error(&format!("{} isn't a number", arg)));
+
+            match first {
+                "replay" => format = Some(number()?),
+                "rules" => version = Some(number()?),
+                "seed" => replay.seed = number()?,
+                "pieces" => replay.pieces = arg.to_string(),
+                "pieces_hash" => pieces_hash = Some(u64::from_str_radix(arg, 16).map_err(|_| error("pieces_hash has to be hex"))?),
+                "randomizer" => replay.rules.randomizer = Randomizers::from_name(arg).ok_or_else(|| error("Unknown randomizer"))?,
+                "scoring" => replay.rules.scoring = ScoringRules::from_name(arg).ok_or_else(|| error("Unknown scoring"))?,
+                "previews" => replay.rules.previews = number()? as usize,
+                "lock_delay" => replay.rules.lock_delay = Duration::from_millis(number()?),
+                "lock_resets" => replay.rules.lock_resets = number()? as u32,
+                "level" => replay.rules.start_level = number()? as u32,
+                "level_lines" => replay.rules.level_lines = number()? as u32,
+                "board" => {
+                    let board = || -> Option<(usize, usize, usize)> {
+                        let (size, buffer) = arg.split_once('+')?;
+                        let (width, height) = size.split_once('x')?;
+                        Some((width.parse().ok()?, height.parse().ok()?, buffer.parse().ok()?))
+                    };
+                    let (width, height, buffer) = board().ok_or_else(|| error("board has to be like 10x20+20"))?;
+                    replay.rules.width = width;
+                    replay.rules.height = height;
+                    replay.rules.buffer = buffer;
+                },
+                "end" => replay.end = number()?,
+                tick => {
+                    let tick: u64 = tick.parse().map_err(|_| error(&format!("Don't know what to do with \"{}\"", line)))?;
+                    if replay.inputs.last().is_some_and(|x| x.0 >= tick) {
+                        return Err(error("Ticks have to go up"))
+                    }
+                    let actions = parse_codes(arg).ok_or_else(|| error("Unknown action"))?;
+                    replay.inputs.push((tick, actions));
+                },
+            }
+        }
+
+        match format {
+            Some(FORMAT) => (),
+            Some(n) => return Err(format!("Can't read replay version {}", n)),
+            None => return Err("Not a replay".to_string()),
+        }
+        match version {
+            Some(n) if n == RULES_VERSION as u64 => (),
+            Some(n) => return Err(format!("Recorded with rules version {}, these are {}", n, RULES_VERSION)),
+            None => return Err("There is no rules version".to_string()),
+        }
+        replay.pieces_hash = pieces_hash.ok_or_else(|| "There is no pieces hash".to_string())?;
+        replay.rules.check()?;
+        Ok(replay)
+    }
+}
+
+fn code(action: Action) -> Option<char> {
+    CODES.iter().find(|x| x.0 == action).map(|x| x.1)
+}
+
+fn action(code: char) -> Option<Action> {
+    CODES.iter().find(|x| x.1 == code).map(|x| x.0)
+}
+
+// The letters for a tick's actions, with how many times after any given more than once in a row
+fn codes(actions: &[Action]) -> String {
+    let mut text = String::new();
+    let mut codes = actions.iter().filter_map(|x| code(*x)).peekable();
+    while let Some(code) = codes.next() {
+        let mut count = 1;
+        while codes.next_if_eq(&code).is_some() {
+            count += 1;
+        }
+        text.push(code);
+        if count > 1 {
+            text += &count.to_string();
+        }
+    }
+    text
+}
+
+fn parse_codes(text: &str) -> Option<Vec<Action>> {
+    let mut actions = vec![];
+    let mut chars = text.chars().peekable();
+    while let Some(code) = chars.next() {
+        let action = action(code)?;
+        let mut count = String::new();
+        while let Some(digit) = chars.next_if(|x| x.is_ascii_digit()) {
+            count.push(digit);
+        }
+        let count = if count.is_empty() {1} else {count.parse().ok().filter(|x| (1..=MAX_COUNT).contains(x))?};
+        actions.extend(std::iter::repeat_n(action, count));
+    }
+    Some(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::GameState;
+    use crate::tetris::set::PieceSet;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use std::rc::Rc;
+
+    const HEADER: &str = "replay 1\nrules 2\nseed 5\npieces assets/pieces.txt\npieces_hash 1f\n";
+
+    #[test]
+    fn pieces_can_have_spaces() {
+        let replay = Replay::parse("replay 1\nrules 2\npieces my pieces.txt\npieces_hash 1f\n").unwrap();
+        assert_eq!(replay.get_pieces(), "my pieces.txt");
+        assert_eq!(replay.get_pieces_hash(), 0x1f);
+    }
+
+    #[test]
+    fn boards_out_of_range_are_rejected() {
+        for board in ["10x200+20", "0x20+20", "10x20+100"] {
+            let text = format!("{}board {}\n", HEADER, board);
+            assert!(Replay::parse(&text).is_err(), "{}", board);
+        }
+        assert!(Replay::parse(&format!("{}previews 0\n", HEADER)).is_err());
+        assert!(Replay::parse(&format!("{}level_lines 0\n", HEADER)).is_err());
+    }
+
+    #[test]
+    fn repeats_are_counted() {
+        let actions = [vec![Action::Left; 60], vec![Action::RotateLeft, Action::Left, Action::Left]].concat();
+        assert_eq!(codes(&actions), "L60QL2");
+        assert_eq!(parse_codes("L60QL2"), Some(actions));
+        assert_eq!(parse_codes("L0"), None);
+        assert_eq!(parse_codes("X"), None);
+    }
+
+    #[test]
+    fn saved_replay_plays_the_same() {
+        let mut rules = Rules::new();
+        rules.randomizer = Randomizers::Bag;
+        rules.scoring = ScoringRules::Guideline;
+        let pieces = Rc::new(PieceSet::standard());
+        let mut state = GameState::new(7, rules, pieces.clone());
+        let mut replay = Replay::new(7, rules, "assets/pieces.txt", pieces.get_hash());
+
+        // Mash random actions until the game is lost
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        while !state.is_lost() && state.get_ticks() < 20000 {
+            let actions: Vec<Action> = (0..rng.gen_range(0..3)).map(|_| CODES[rng.gen_range(0..CODES.len())].0).collect();
+            replay.record(state.get_ticks(), &actions);
+            state.step(&actions);
+        }
+        replay.finish(state.get_ticks());
+
+        let path = std::env::temp_dir().join(format!("tetris-test-{}.replay", std::process::id()));
+        let path = path.to_str().unwrap();
+        replay.save(path).unwrap();
+        let loaded = Replay::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded.get_pieces_hash(), pieces.get_hash());
+        let mut again = GameState::new(loaded.get_seed(), loaded.get_rules(), pieces);
+        while again.get_ticks() < loaded.get_end() {
+            again.step(loaded.get_actions(again.get_ticks()));
+        }
+        assert_eq!(again.get_ticks(), state.get_ticks());
+        assert_eq!(again.get_score(), state.get_score());
+        assert_eq!(again.get_lines(), state.get_lines());
+        assert_eq!(again.get_placed(), state.get_placed());
+        assert_eq!(again.is_lost(), state.is_lost());
+        assert_eq!(again.get_tetris().return_grid(), state.get_tetris().return_grid());
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        let errors = [
+            "rules 2\n", // No format line
+            "replay 99\nrules 2\n",
+            "replay 1\n", // No rules version
+            "replay 1\nrules 2\nseed x\n",
+            "replay 1\nrules 2\nrandomizer dice\n",
+            "replay 1\nrules 2\nboard 10by20\n",
+            "replay 1\nrules 2\n5 LZ\n",
+            "replay 1\nrules 2\n5 L\n3 R\n", // Ticks going back
+            "replay 1\nrules 2\nspeed 3\n",
+            "replay 1\nrules 2\npieces a.txt\n", // No pieces hash
+            "replay 1\nrules 2\npieces_hash xyz\n",
+        ];
+        for text in errors {
+            assert!(Replay::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// How many scores are kept
+const KEEP: usize = 100;
+// How many scores a table shows
+pub const TOP: usize = 10;
+
+// One finished game
+#[derive(Clone, Debug, PartialEq)]
+pub struct Score {
+    pub name: String,
+    pub score: u64,
+    pub lines: u32,
+    pub level: u32,
+    pub duration: Duration, // How long the game was played
+    pub mode: String, // The rules the game was played with, see Rules::get_mode
+    pub date: u64, // When the game ended, in seconds since 1970
+}
+impl Score {
+    // A score for a game that just ended
+    pub fn new(name: &str, score: u64, lines: u32, level: u32, duration: Duration, mode: &str) -> Score {
+        Score{
+            name: name.to_string(),
+            score,
+            lines,
+            level,
+            duration,
+            mode: mode.to_string(),
+            date: SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0),
+        }
+    }
+
+    fn to_toml(&self) -> toml::Table {
+        let mut table = toml::Table::new();
+        table.insert("name".to_string(), toml::Value::from(self.name.as_str()));
+        table.insert("score".to_string(), toml::Value::from(self.score as i64));
+        table.insert("lines".to_string(), toml::Value::from(self.lines as i64));
+        table.insert("level".to_string(), toml::Value::from(self.level as i64));
+        table.insert("duration".to_string(), toml::Value::from(self.duration.as_millis() as i64));
+        table.insert("mode".to_string(), toml::Value::from(self.mode.as_str()));
+        table.insert("date".to_string(), toml::Value::from(self.date as i64));
+        table
+    }
+
+    fn from_toml(table: &toml::Table) -> Option<Score> {
+        let number = |name: &str| table.get(name)?.as_integer().filter(|x| *x >= 0);
+        Some(Score{
+            name: table.get("name")?.as_str()?.to_string(),
+            score: number("score")? as u64,
+            lines: number("lines")? as u32,
+            level: number("level")? as u32,
+            duration: Duration::from_millis(number("duration")? as u64),
+            mode: table.get("mode")?.as_str()?.to_string(),
+            date: number("date")? as u64,
+        })
+    }
+}
+
+// The best scores, best first
+// Kept in the data directory as TOML, a [[score]] table for each
+pub struct Scores {
+    path: Option<PathBuf>, // None if there's nowhere to save them
+    scores: Vec<Score>,
+}
+impl Scores {
+    // Where scores are kept, like ~/.local/share/tetris/scores.toml
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|x| x.join("tetris").join("scores.toml"))
+    }
+
+    // Load the scores, or start over without them
+    // A file that can't be read is moved out of the way, not written over
+    pub fn load(path: Option<PathBuf>) -> Scores {
+        let mut scores = Scores{
+            path: path.clone(),
+            scores: vec![],
+        };
+        let path = match path {
+            Some(path) if path.exists() => path,
+            _ => return scores,
+        };
+
+        let read = fs::read_to_string(&path).map_err(|e| e.to_string())
+            .and_then(|text| Scores::parse(&text));
+        match read {
+            Ok(list) => scores.scores = list,
+            Err(e) => {
+                let broken = path.with_extension("toml.broken");
+                eprintln!("{}: {}, moving it to {}", path.display(), e, broken.display());
+                if fs::rename(&path, &broken).is_err() {
+                    // Don't lose the old scores by saving over them
+                    scores.path = None;
+                }
+            },
+        }
+        scores
+    }
+
+    fn parse(text: &str) -> Result<Vec<Score>, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string().lines().next().unwrap_or("").to_string())?;
+        let list = match table.get("score") {
+            Some(list) => list.as_array().ok_or_else(|| "score has to be a list".to_string())?,
+            None => return Ok(vec![]),
+        };
+
+        let mut scores = vec![];
+        for (n, score) in list.iter().enumerate() {
+            let score = score.as_table().and_then(Score::from_toml)
+                .ok_or_else(|| format!("score {} is missing something", n + 1))?;
+            scores.push(score);
+        }
+        scores.sort_by_key(|x| Reverse(x.score));
+        Ok(scores)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let list = self.scores.iter().map(|x| toml::Value::Table(x.to_toml())).collect();
+        let mut table = toml::Table::new();
+        table.insert("score".to_string(), toml::Value::Array(list));
+
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| error(&e))?;
+        }
+        let text = toml::to_string(&table).map_err(|e| error(&e))?;
+        fs::write(path, text).map_err(|e| error(&e))
+    }
+
+    // Add a score, return where it placed if it's kept
+    // Ties go under the scores already there
+    pub fn add(&mut self, score: Score) -> Option<usize> {
+        let place = self.scores.iter().position(|x| x.score < score.score).unwrap_or(self.scores.len());
+        if place >= KEEP {
+            return None
+        }
+        self.scores.insert(place, score);
+        self.scores.truncate(KEEP);
+        Some(place)
+    }
+
+    pub fn get_top(&self, n: usize) -> &[Score] {
+        &self.scores[..n.min(self.scores.len())]
+    }
+
+    // The best n scores as text, with a > next to the one at mark
+    pub fn table(&self, n: usize, mark: Option<usize>) -> String {
+        let mut text = format!("   {:>3}  {:<12} {:>9} {:>6} {:>6} {:>7}  {:<24} {}\n", "#", "Name", "Score", "Lines", "Level", "Time", "Mode", "Date");
+        for (i, score) in self.get_top(n).iter().enumerate() {
+            let seconds = score.duration.as_secs();
+            text += &format!(" {} {:>3}  {:<12} {:>9} {:>6} {:>6} {:>4}:{:02}  {:<24} {}\n",
+                if mark == Some(i) {">"} else {" "},
+                i + 1,
+                score.name.chars().take(12).collect::<String>(),
+                score.score,
+                score.lines,
+                score.level,
+                seconds / 60,
+                seconds % 60,
+                score.mode,
+                date(score.date));
+        }
+        if self.scores.is_empty() {
+            text += "   No scores yet\n";
+        }
+        text
+    }
+}
+
+// A time in seconds since 1970 as year-month-day
+pub fn date(seconds: u64) -> String {
+    // Days to a date, from howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 {mp + 3} else {mp - 9};
+    let year = year_of_era + era * 400 + if month <= 2 {1} else {0};
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(name: &str, points: u64) -> Score {
+        Score{
+            name: name.to_string(),
+            score: points,
+            lines: 10,
+            level: 2,
+            duration: Duration::from_secs(90),
+            mode: "guideline uniform 10x20".to_string(),
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn best_first_and_ties_go_under() {
+        let mut scores = Scores::load(None);
+        assert_eq!(scores.add(score("a", 100)), Some(0));
+        assert_eq!(scores.add(score("b", 300)), Some(0));
+        assert_eq!(scores.add(score("c", 100)), Some(2));
+        let names: Vec<&str> = scores.get_top(TOP).iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "c"]);
+        assert_eq!(scores.get_top(1).len(), 1);
+    }
+
+    #[test]
+    fn only_the_best_are_kept() {
+        let mut scores = Scores::load(None);
+        for n in 0..KEEP {
+            scores.add(score("a", 100 + n as u64));
+        }
+        assert_eq!(scores.add(score("b", 1)), None);
+        assert_eq!(scores.add(score("c", 1000)), Some(0));
+        assert_eq!(scores.scores.len(), KEEP);
+    }
+
+    #[test]
+    fn saved_scores_read_back() {
+        let list = [score("a", 5), score("b", 50)];
+        let mut table = toml::Table::new();
+        table.insert("score".to_string(), toml::Value::Array(list.iter().map(|x| toml::Value::Table(x.to_toml())).collect()));
+        let text = toml::to_string(&table).unwrap();
+        assert_eq!(Scores::parse(&text), Ok(vec![score("b", 50), score("a", 5)]));
+        assert_eq!(Scores::parse(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        assert!(Scores::parse("score = 5").is_err());
+        assert!(Scores::parse("[[score]]\nname = \"a\"\n").is_err());
+        assert!(Scores::parse("[[score]").is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951782400), "2000-02-29");
+        assert_eq!(date(1735689599), "2024-12-31");
+    }
+}
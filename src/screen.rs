@@ -0,0 +1,174 @@
+pub mod title;
+pub mod mode;
+pub mod options;
+pub mod keys;
+pub mod high_scores;
+pub mod playing;
+pub mod pause;
+pub mod game_over;
+
+use crate::{Assets, Game};
+use crate::input::{Action, ActionEvent};
+
+use sfml::graphics::*;
+use sfml::system::Vector2f;
+
+// Where menus are drawn unless they're moved, the middle of the window
+// each item is a bar under the last
+const MENU: (f32, f32) = (120.0, 240.0);
+const MENU_ITEM: (f32, f32) = (240.0, 28.0);
+const MENU_SPACING: f32 = 40.0;
+// How big menu text is
+const MENU_TEXT: u32 = 16;
+// Where headings are drawn, they're centered on it
+const HEADING: (f32, f32) = (240.0, 32.0);
+const HEADING_TEXT: u32 = 32;
+
+// What the Game should do with the screens after a screen has had its turn
+pub enum Change {
+    None,
+    Push(Box<dyn Screen>), // Open a screen over this one
+    Pop, // Close this screen, going back to the one under it
+    Replace(Box<dyn Screen>), // Close this screen and open another in its place
+    ReplaceUnder(Box<dyn Screen>), // Close this screen and the one under it, and open another in their place
+    Home, // Close every screen but the first, the title
+    Quit, // Close every screen
+}
+
+// One part of the game, like a menu or the board being played
+// Only the screen on top of the stack is given events and ticks, and drawn
+pub trait Screen {
+    // Something was pressed or let go
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change;
+
+    // A tick has passed while this screen is on top
+    fn tick(&mut self, _game: &mut Game) -> Change {
+        Change::None
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets);
+
+    // If nothing changes until something is pressed
+    // so the game can sleep until then instead of ticking
+    fn is_waiting(&self) -> bool {
+        false
+    }
+
+    // The screen is now on top, or is again after the game was away from
+    // the window, keys held until now won't be seen being let go
+    fn resume(&mut self, _game: &mut Game) {}
+
+    // The screen is being taken off the stack
+    fn close(&mut self, _game: &mut Game) {}
+
+    // If this is where keys are rebound, so Rebind doesn't open it again
+    fn is_rebinding(&self) -> bool {
+        false
+    }
+}
+
+// What was done to a menu
+pub enum Choice {
+    Select(usize),
+    Left(usize),
+    Right(usize),
+    Back,
+}
+
+// A list of items, one of them chosen
+// Up and Down change which, Left and Right change its value if it has one
+pub struct Menu {
+    items: Vec<String>,
+    selected: usize,
+    origin: (f32, f32), // The top left of the first item
+    width: f32,
+    spacing: f32, // From the top of one item to the top of the next
+}
+impl Menu {
+    pub fn new(items: &[&str]) -> Menu {
+        Menu{
+            items: items.iter().map(|x| x.to_string()).collect(),
+            selected: 0,
+            origin: (MENU.0, MENU.1 - items.len() as f32 * MENU_SPACING / 2.0),
+            width: MENU_ITEM.0,
+            spacing: MENU_SPACING,
+        }
+    }
+
+    pub fn set_origin(&mut self, origin: (f32, f32)) {
+        self.origin = origin;
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width;
+    }
+
+    pub fn set_spacing(&mut self, spacing: f32) {
+        self.spacing = spacing;
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn set_label(&mut self, n: usize, label: &str) {
+        self.items[n] = label.to_string();
+    }
+
+    pub fn event(&mut self, event: &ActionEvent) -> Option<Choice> {
+        if !event.pressed {
+            return None
+        }
+        match event.action {
+            Action::Up => self.selected = (self.selected + self.items.len() - 1) % self.items.len(),
+            Action::Down => self.selected = (self.selected + 1) % self.items.len(),
+            Action::Left => return Some(Choice::Left(self.selected)),
+            Action::Right => return Some(Choice::Right(self.selected)),
+            Action::Select => return Some(Choice::Select(self.selected)),
+            Action::Quit => return Some(Choice::Back),
+            _ => (),
+        }
+        None
+    }
+
+    // A bar for each item with its label on it, the chosen one lit up
+    pub fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        // Rows closer than a bar is high get thinner bars, so they don't overlap
+        let mut bar = RectangleShape::with_size(Vector2f::new(self.width, MENU_ITEM.1.min(self.spacing - 2.0)));
+        for (n, item) in self.items.iter().enumerate() {
+            let (fill, color) = if n == self.selected {
+                (Color::rgb(200, 200, 200), Color::BLACK)
+            } else {
+                (Color::rgb(60, 60, 60), Color::WHITE)
+            };
+            let position = (self.origin.0, self.origin.1 + n as f32 * self.spacing);
+            bar.set_fill_color(fill);
+            bar.set_position(position);
+            window.draw(&bar);
+            draw_text(window, assets, item, (position.0 + 8.0, position.1 + 4.0), MENU_TEXT, color);
+        }
+    }
+}
+
+// Draw a line of text with its top left at position
+pub fn draw_text(window: &mut RenderWindow, assets: &Assets, string: &str, position: (f32, f32), size: u32, color: Color) {
+    let mut text = Text::new(string, assets.get_font(), size);
+    text.set_fill_color(color);
+    text.set_position(position);
+    window.draw(&text);
+}
+
+// What the screen is, big and centered at the top
+pub fn draw_heading(window: &mut RenderWindow, assets: &Assets, string: &str) {
+    let mut text = Text::new(string, assets.get_font(), HEADING_TEXT);
+    let bounds = text.local_bounds();
+    text.set_position((HEADING.0 - bounds.width / 2.0 - bounds.left, HEADING.1));
+    window.draw(&text);
+}
+
+// Darken what's drawn so far, so a menu stands out over it
+fn draw_shade(window: &mut RenderWindow) {
+    let mut shade = RectangleShape::with_size(Vector2f::new(480.0, 480.0));
+    shade.set_fill_color(Color::rgba(0, 0, 0, 160));
+    window.draw(&shade);
+}
@@ -0,0 +1,206 @@
+use crate::{Assets, Game};
+use crate::input::ActionEvent;
+use crate::screen::{Change, Choice, Menu, Screen};
+use crate::screen::playing::{self, Playing};
+use crate::scores::Score;
+use crate::state::{GameState, TICK};
+use crate::tetris::{piece, Cell, Tetris};
+
+use std::time::Duration;
+use sfml::graphics::*;
+use sfml::system::Vector2f;
+
+// How many ticks each row takes to turn grey when the game is lost
+const ROW_TICKS: u32 = 3;
+// Where the menu goes, at the bottom of the side panel
+const MENU: (f32, f32) = (256.0, 392.0);
+const MENU_WIDTH: f32 = 208.0;
+// Where the score and the rest go, a line for each, over the board
+const STATS: (f32, f32) = (16.0, 88.0);
+const STATS_TEXT: u32 = 12;
+const STATS_SPACING: f32 = 18.0;
+// The part of the side panel the piece histogram is drawn in
+const HISTOGRAM: (f32, f32, f32, f32) = (268.0, 88.0, 204.0, 140.0);
+// Where the high scores go, under the histogram
+const TABLE: (f32, f32) = (268.0, 240.0);
+const TABLE_SPACING: f32 = 14.0;
+
+// Shown once a game is lost
+// The board fills up with grey from the bottom, then how the game went is shown
+pub struct GameOver {
+    menu: Menu,
+    tetris: Tetris,
+    rows: usize, // How many rows have turned grey, from the bottom
+    ticks: u32, // Since the last row turned grey
+
+    score: u64,
+    lines: u32,
+    level: u32,
+    time: Duration,
+    placed: u64,
+    seed: u64, // So the same pieces can be played again
+    clears: Vec<(String, u32)>, // How many times each clear happened
+    kinds: Vec<(String, piece::Piece, u32)>, // How many of each piece were placed
+    place: Option<usize>, // Where the score placed in the high scores
+    table: Vec<String>, // The best scores, with this one marked if it's there
+}
+impl GameOver {
+    pub fn new(state: &GameState, place: Option<usize>, top: &[Score]) -> GameOver {
+        // Show the piece that didn't fit, where it ended up
+        let mut tetris = state.get_tetris().clone();
+        let last = state.get_piece();
+        for pos in last.get_bits_pos() {
+            tetris.set_grid(pos, Cell::Filled{kind: last.get_kind(), color: last.get_color()});
+        }
+
+        let pieces = state.get_pieces();
+        let kinds = state.get_kinds().iter().enumerate().map(|(kind, count)| {
+            let def = pieces.get(kind);
+            let piece = piece::Piece::new(kind, def, def.get_color().unwrap_or(kind % piece::COLORS));
+            (def.get_name().to_string(), piece, *count)
+        }).collect();
+        let clears = state.get_clears().iter()
+            .filter_map(|(clear, count)| Some((clear.get_message()?, *count)))
+            .collect();
+
+        let mut table = vec![format!(" {:>2} {:<10} {:>8}", "#", "Name", "Score")];
+        for (n, score) in top.iter().enumerate() {
+            let mark = if place == Some(n) {">"} else {" "};
+            table.push(format!("{}{:>2} {:<10} {:>8}", mark, n + 1, score.name.chars().take(10).collect::<String>(), score.score));
+        }
+
+        let mut menu = Menu::new(&["Retry", "Menu"]);
+        menu.set_origin(MENU);
+        menu.set_width(MENU_WIDTH);
+        GameOver{
+            menu,
+            tetris,
+            rows: 0,
+            ticks: 0,
+            score: state.get_score(),
+            lines: state.get_lines(),
+            level: state.get_level(),
+            time: TICK * state.get_ticks() as u32,
+            placed: state.get_placed(),
+            seed: state.get_seed(),
+            clears,
+            kinds,
+            place,
+            table,
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.rows < self.tetris.get_height()
+    }
+
+    // Turn the next row up grey
+    fn fill_row(&mut self) {
+        let row = (self.tetris.get_height() - 1 - self.rows) as i8;
+        for x in 0..self.tetris.get_width() as i8 {
+            self.tetris.set_grid(piece::Pos(row, x), Cell::Garbage);
+        }
+        self.rows += 1;
+    }
+
+    fn get_pieces_per_second(&self) -> f64 {
+        if self.time.is_zero() {
+            return 0.0
+        }
+        self.placed as f64 / self.time.as_secs_f64()
+    }
+
+    // How the game went, a line for each thing
+    fn get_stats(&self) -> Vec<String> {
+        let seconds = self.time.as_secs();
+        let mut stats = vec![
+            format!("Score      {}", self.score),
+            format!("Lines      {}", self.lines),
+            format!("Level      {}", self.level),
+            format!("Time       {}:{:02}", seconds / 60, seconds % 60),
+            format!("Pieces     {}", self.placed),
+            format!("Pieces/s   {:.2}", self.get_pieces_per_second()),
+            format!("Seed       {}", self.seed),
+        ];
+        if let Some(place) = self.place {
+            stats.push(format!("#{} high score", place + 1));
+        }
+        stats.push(String::new());
+        for (name, count) in &self.clears {
+            stats.push(format!("{:>4} {}", count, name));
+        }
+        stats
+    }
+
+    // A bar for each kind of piece, as long as how many were placed next to the most placed
+    fn draw_histogram(&self, window: &mut RenderWindow, assets: &Assets) {
+        let (left, top, width, height) = HISTOGRAM;
+        let most = self.kinds.iter().map(|x| x.2).max().unwrap_or(0).max(1);
+        let row = (height / self.kinds.len().max(1) as f32).min(40.0).floor();
+        let mut bit = RectangleShape::new();
+        for (n, (_, piece, count)) in self.kinds.iter().enumerate() {
+            let y = top + n as f32 * row;
+            bit.set_texture(assets.get_bit(piece.get_color()), false);
+            playing::draw_piece(window, &mut bit, piece, (left, y), (row - 4.0) / 5.0);
+
+            // Leave room for the count after the longest bar
+            let start = left + row + 4.0;
+            let length = (left + width - 40.0 - start) * *count as f32 / most as f32;
+            bit.set_size(Vector2f::new(length, row / 2.0));
+            bit.set_position((start, y + row / 4.0));
+            window.draw(&bit);
+            super::draw_text(window, assets, &count.to_string(), (start + length + 4.0, y + row / 4.0), STATS_TEXT, Color::WHITE);
+        }
+    }
+}
+impl Screen for GameOver {
+    // Anything pressed skips to the end of the animation
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        if self.is_animating() {
+            if event.pressed {
+                while self.is_animating() {
+                    self.fill_row();
+                }
+            }
+            return Change::None
+        }
+        match self.menu.event(event) {
+            Some(Choice::Select(0)) => Change::Replace(Box::new(Playing::new(game))),
+            Some(Choice::Select(_)) | Some(Choice::Back) => Change::Home,
+            _ => Change::None,
+        }
+    }
+
+    fn tick(&mut self, _game: &mut Game) -> Change {
+        if self.is_animating() {
+            self.ticks += 1;
+            if self.ticks >= ROW_TICKS {
+                self.ticks = 0;
+                self.fill_row();
+            }
+        }
+        Change::None
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        playing::draw_board(window, assets, &self.tetris);
+        if self.is_animating() {
+            return
+        }
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Game over");
+        for (n, line) in self.get_stats().iter().enumerate() {
+            super::draw_text(window, assets, line, (STATS.0, STATS.1 + n as f32 * STATS_SPACING), STATS_TEXT, Color::WHITE);
+        }
+        self.draw_histogram(window, assets);
+        for (n, line) in self.table.iter().enumerate() {
+            super::draw_text(window, assets, line, (TABLE.0, TABLE.1 + n as f32 * TABLE_SPACING), STATS_TEXT, Color::WHITE);
+        }
+        self.menu.draw(window, assets);
+    }
+
+    fn is_waiting(&self) -> bool {
+        !self.is_animating()
+    }
+}
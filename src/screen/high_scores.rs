@@ -0,0 +1,66 @@
+use crate::{Assets, Game};
+use crate::input::ActionEvent;
+use crate::scores;
+use crate::screen::{Change, Choice, Menu, Screen};
+
+use sfml::graphics::*;
+
+// Where the table starts, the names of the columns go above it
+const TABLE: (f32, f32) = (24.0, 104.0);
+const TABLE_WIDTH: f32 = 432.0;
+const ROW_SPACING: f32 = 32.0;
+// Where the rules and date of the chosen score are shown
+const DETAILS: (f32, f32) = (32.0, 440.0);
+
+// The best scores, the chosen one's rules and date are shown under them
+pub struct HighScores {
+    menu: Menu,
+    details: Vec<String>,
+}
+impl HighScores {
+    pub fn new(game: &Game) -> HighScores {
+        let top = game.scores.get_top(scores::TOP);
+        let mut rows: Vec<String> = top.iter().enumerate().map(|(n, score)| {
+            let seconds = score.duration.as_secs();
+            format!("{:>2}. {:<12} {:>9} {:>5} {:>3} {:>3}:{:02}",
+                n + 1, score.name.chars().take(12).collect::<String>(), score.score, score.lines, score.level, seconds / 60, seconds % 60)
+        }).collect();
+        let mut details: Vec<String> = top.iter().map(|x| format!("{}  {}", x.mode, scores::date(x.date))).collect();
+        if rows.is_empty() {
+            rows.push("No scores yet".to_string());
+            details.push(String::new());
+        }
+
+        let rows: Vec<&str> = rows.iter().map(|x| x.as_str()).collect();
+        let mut menu = Menu::new(&rows);
+        menu.set_origin(TABLE);
+        menu.set_width(TABLE_WIDTH);
+        menu.set_spacing(ROW_SPACING);
+        HighScores{
+            menu,
+            details,
+        }
+    }
+}
+impl Screen for HighScores {
+    fn event(&mut self, _game: &mut Game, event: &ActionEvent) -> Change {
+        match self.menu.event(event) {
+            Some(Choice::Select(_)) | Some(Choice::Back) => Change::Pop,
+            _ => Change::None,
+        }
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "High scores");
+        let header = format!("{:>2}  {:<12} {:>9} {:>5} {:>3} {:>6}", "#", "Name", "Score", "Lines", "Lvl", "Time");
+        super::draw_text(window, assets, &header, (TABLE.0 + 8.0, TABLE.1 - 24.0), super::MENU_TEXT, Color::WHITE);
+        self.menu.draw(window, assets);
+        super::draw_text(window, assets, &self.details[self.menu.get_selected()], DETAILS, super::MENU_TEXT, Color::WHITE);
+    }
+
+    fn is_waiting(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Assets, Game};
+use crate::input::{Action, ActionEvent, BINDABLE};
+use crate::input::keymap::Bind;
+use crate::screen::{Change, Choice, Menu, Screen};
+
+use sfml::graphics::*;
+use sfml::window::Key;
+
+// Where the actions start, one on each row, Back under them
+const TABLE: (f32, f32) = (24.0, 70.0);
+const TABLE_WIDTH: f32 = 432.0;
+const ROW_SPACING: f32 = 26.0;
+// How many letters fit on a row
+const ROW_LENGTH: usize = 43;
+// What to do next, under the table
+const HINT: (f32, f32) = (24.0, 462.0);
+const HINT_TEXT: u32 = 12;
+
+const BACK: usize = BINDABLE.len();
+
+// Every action and what it's bound to, choosing one waits for something
+// to be pressed and binds it in place of the ones of the same kind
+pub struct Keys {
+    menu: Menu,
+    waiting: Option<Action>, // The action that's being bound
+}
+impl Keys {
+    pub fn new(game: &Game) -> Keys {
+        let mut items = vec![""; BINDABLE.len()];
+        items.push("Back");
+        let mut keys = Keys{
+            menu: Menu::new(&items),
+            waiting: None,
+        };
+        keys.menu.set_origin(TABLE);
+        keys.menu.set_width(TABLE_WIDTH);
+        keys.menu.set_spacing(ROW_SPACING);
+        keys.update(game);
+        keys
+    }
+
+    fn update(&mut self, game: &Game) {
+        let keymap = game.input.get_keymap();
+        for (n, action) in BINDABLE.into_iter().enumerate() {
+            let binds = if self.waiting == Some(action) {
+                "...".to_string()
+            } else {
+                keymap.get_binds(action).iter().map(|x| x.get_name()).collect::<Vec<String>>().join(", ")
+            };
+            let label: String = format!("{:<13}{}", action.get_name(), binds).chars().take(ROW_LENGTH).collect();
+            self.menu.set_label(n, &label);
+        }
+    }
+
+    // Bind what was pressed to the action and save the keymap
+    // Escape keeps the binds it had
+    fn bind(&mut self, game: &mut Game, action: Action, bind: Bind) {
+        if bind != Bind::Key(Key::Escape) {
+            let mut keymap = game.input.get_keymap().clone();
+            keymap.rebind(action, bind);
+            if let Err(e) = keymap.save(&game.keymap) {
+                eprintln!("{}", e);
+            }
+            game.input.set_keymap(keymap);
+        }
+        self.waiting = None;
+        self.update(game);
+    }
+}
+impl Screen for Keys {
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        if self.waiting.is_some() {
+            return Change::None
+        }
+        match self.menu.event(event) {
+            Some(Choice::Select(BACK)) | Some(Choice::Back) => Change::Pop,
+            Some(Choice::Select(n)) => {
+                self.waiting = Some(BINDABLE[n]);
+                game.input.capture();
+                self.update(game);
+                Change::None
+            },
+            _ => Change::None,
+        }
+    }
+
+    fn tick(&mut self, game: &mut Game) -> Change {
+        if let Some(action) = self.waiting {
+            if let Some(bind) = game.input.take_capture() {
+                self.bind(game, action, bind);
+            }
+        }
+        Change::None
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Keys");
+        self.menu.draw(window, assets);
+        let hint = if self.waiting.is_some() {
+            "Press a key, button or stick, Escape keeps the old ones"
+        } else {
+            "Choose an action to bind something else to it"
+        };
+        super::draw_text(window, assets, hint,HINT, HINT_TEXT, Color::rgb(160, 160, 160));
+    }
+
+    // Ticks are needed to see what's pressed while waiting for it
+    fn is_waiting(&self) -> bool {
+        self.waiting.is_none()
+    }
+
+    fn is_rebinding(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Assets, Game};
+use crate::input::ActionEvent;
+use crate::screen::{Change, Choice, Menu, Screen};
+use crate::screen::playing::Playing;
+use crate::tetris::randomizer::Randomizers;
+use crate::tetris::scoring::ScoringRules;
+
+use sfml::graphics::*;
+
+const RANDOMIZERS: [Randomizers; 3] = [Randomizers::Uniform, Randomizers::Bag, Randomizers::History];
+const SCORING: [ScoringRules; 2] = [ScoringRules::Guideline, ScoringRules::Classic];
+// The board sizes that can be picked, columns then rows
+const BOARDS: [(usize, usize); 5] = [(10, 20), (6, 12), (10, 40), (20, 20), (40, 60)];
+const MAX_LEVEL: u32 = 20;
+
+const RANDOMIZER: usize = 0;
+const SCORING_RULES: usize = 1;
+const LEVEL: usize = 2;
+const BOARD: usize = 3;
+const PREVIEWS: usize = 4;
+const PLAY: usize = 5;
+
+// Pick the rules before playing
+// Left and Right change them, they're kept for every game after
+pub struct ModeSelect {
+    menu: Menu,
+}
+impl ModeSelect {
+    pub fn new(game: &Game) -> ModeSelect {
+        let mut mode = ModeSelect{
+            menu: Menu::new(&["", "", "", "", "", "Play"]),
+        };
+        mode.update(game);
+        mode
+    }
+
+    fn update(&mut self, game: &Game) {
+        let rules = game.rules;
+        self.menu.set_label(RANDOMIZER, &format!("Randomizer: {}", rules.randomizer.get_name()));
+        self.menu.set_label(SCORING_RULES, &format!("Scoring: {}", rules.scoring.get_name()));
+        self.menu.set_label(LEVEL, &format!("Level: {}", rules.start_level));
+        self.menu.set_label(BOARD, &format!("Board: {}x{}", rules.width, rules.height));
+        self.menu.set_label(PREVIEWS, &format!("Previews: {}", rules.previews));
+    }
+
+    // Change the chosen rule to the next or last value
+    fn change(&mut self, game: &mut Game, n: usize, forward: bool) {
+        let rules = &mut game.rules;
+        match n {
+            RANDOMIZER => {
+                let now = RANDOMIZERS.iter().position(|x| *x == rules.randomizer);
+                rules.randomizer = RANDOMIZERS[cycle(now, RANDOMIZERS.len(), forward)];
+            },
+            SCORING_RULES => {
+                let now = SCORING.iter().position(|x| *x == rules.scoring);
+                rules.scoring = SCORING[cycle(now, SCORING.len(), forward)];
+            },
+            LEVEL => rules.start_level = cycle(Some(rules.start_level.max(1) as usize - 1), MAX_LEVEL as usize, forward) as u32 + 1,
+            BOARD => {
+                let now = BOARDS.iter().position(|x| *x == (rules.width, rules.height));
+                (rules.width, rules.height) = BOARDS[cycle(now, BOARDS.len(), forward)];
+            },
+            PREVIEWS => rules.previews = cycle(Some(rules.previews.clamp(1, 6) - 1), 6, forward) + 1,
+            _ => (),
+        }
+        self.update(game);
+    }
+}
+impl Screen for ModeSelect {
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        match self.menu.event(event) {
+            Some(Choice::Select(PLAY)) => Change::Replace(Box::new(Playing::new(game))),
+            Some(Choice::Select(n)) | Some(Choice::Right(n)) => {self.change(game, n, true); Change::None},
+            Some(Choice::Left(n)) => {self.change(game, n, false); Change::None},
+            Some(Choice::Back) => Change::Pop,
+            None => Change::None,
+        }
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Mode");
+        self.menu.draw(window, assets);
+    }
+
+    fn is_waiting(&self) -> bool {
+        true
+    }
+}
+
+// The next or last of len values, starting from the first if now isn't one of them
+fn cycle(now: Option<usize>, len: usize, forward: bool) -> usize {
+    match (now, forward) {
+        (None, _) => 0,
+        (Some(n), true) => (n + 1) % len,
+        (Some(n), false) => (n + len - 1) % len,
+    }
+}
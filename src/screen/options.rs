@@ -0,0 +1,81 @@
+use crate::{Assets, Game};
+use crate::input::ActionEvent;
+use crate::screen::{Change, Choice, Menu, Screen};
+use crate::screen::keys::Keys;
+
+use std::time::Duration;
+use sfml::graphics::*;
+
+// How much Left and Right change DAS and ARR by
+const STEP: Duration = Duration::from_millis(10);
+const MAX_DAS: Duration = Duration::from_millis(500);
+const MAX_ARR: Duration = Duration::from_millis(200);
+// How many times faster than gravity soft drop can be
+const SOFT_DROP: [f64; 6] = [1.0, 2.0, 5.0, 10.0, 20.0, 40.0];
+
+const DAS: usize = 0;
+const ARR: usize = 1;
+const SOFT_DROP_FACTOR: usize = 2;
+const KEYS: usize = 3;
+const BACK: usize = 4;
+
+// How held keys repeat, and rebinding them
+pub struct Options {
+    menu: Menu,
+}
+impl Options {
+    pub fn new(game: &Game) -> Options {
+        let mut options = Options{
+            menu: Menu::new(&["", "", "", "Keys", "Back"]),
+        };
+        options.update(game);
+        options
+    }
+
+    fn update(&mut self, game: &Game) {
+        let timing = game.timing;
+        self.menu.set_label(DAS, &format!("DAS: {} ms", timing.das.as_millis()));
+        self.menu.set_label(ARR, &format!("ARR: {} ms", timing.arr.as_millis()));
+        self.menu.set_label(SOFT_DROP_FACTOR, &format!("Soft drop: {}x", timing.soft_drop));
+    }
+
+    fn change(&mut self, game: &mut Game, n: usize, forward: bool) {
+        let timing = &mut game.timing;
+        let step = |x: Duration, max: Duration| if forward {(x + STEP).min(max)} else {x.saturating_sub(STEP)};
+        match n {
+            DAS => timing.das = step(timing.das, MAX_DAS),
+            ARR => timing.arr = step(timing.arr, MAX_ARR),
+            SOFT_DROP_FACTOR => {
+                timing.soft_drop = if forward {
+                    SOFT_DROP.iter().copied().find(|x| *x > timing.soft_drop).unwrap_or(SOFT_DROP[0])
+                } else {
+                    SOFT_DROP.iter().copied().rev().find(|x| *x < timing.soft_drop).unwrap_or(SOFT_DROP[SOFT_DROP.len() - 1])
+                };
+            },
+            _ => (),
+        }
+        self.update(game);
+    }
+}
+impl Screen for Options {
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        match self.menu.event(event) {
+            Some(Choice::Select(KEYS)) => Change::Push(Box::new(Keys::new(game))),
+            Some(Choice::Select(BACK)) | Some(Choice::Back) => Change::Pop,
+            Some(Choice::Select(n)) | Some(Choice::Right(n)) => {self.change(game, n, true); Change::None},
+            Some(Choice::Left(n)) => {self.change(game, n, false); Change::None},
+            None => Change::None,
+        }
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Options");
+        self.menu.draw(window, assets);
+    }
+
+    fn is_waiting(&self) -> bool {
+        true
+    }
+}
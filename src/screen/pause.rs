@@ -0,0 +1,50 @@
+use crate::{Assets, Game};
+use crate::input::{Action, ActionEvent};
+use crate::screen::{Change, Choice, Menu, Screen};
+use crate::screen::options::Options;
+use crate::screen::playing::Playing;
+
+use sfml::graphics::*;
+
+// Opened over a game, which doesn't step while it's open
+// The board isn't drawn, so it can't be looked at while the game is stopped
+pub struct Pause {
+    menu: Menu,
+}
+impl Pause {
+    pub fn new() -> Pause {
+        Pause{
+            menu: Menu::new(&["Resume", "Restart", "Options", "Quit"]),
+        }
+    }
+}
+impl Default for Pause {
+    fn default() -> Pause {
+        Pause::new()
+    }
+}
+impl Screen for Pause {
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        if event.pressed && event.action == Action::Pause {
+            return Change::Pop
+        }
+        match self.menu.event(event) {
+            Some(Choice::Select(0)) | Some(Choice::Back) => Change::Pop,
+            Some(Choice::Select(1)) => Change::ReplaceUnder(Box::new(Playing::new(game))),
+            Some(Choice::Select(2)) => Change::Push(Box::new(Options::new(game))),
+            Some(Choice::Select(_)) => Change::Home,
+            _ => Change::None,
+        }
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Paused");
+        self.menu.draw(window, assets);
+    }
+
+    fn is_waiting(&self) -> bool {
+        true
+    }
+}
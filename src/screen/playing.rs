@@ -0,0 +1,320 @@
+use crate::{Assets, Game};
+use crate::input::{self, Action, ActionEvent};
+use crate::replay::Replay;
+use crate::scores;
+use crate::screen::{Change, Screen};
+use crate::screen::game_over::GameOver;
+use crate::screen::pause::Pause;
+use crate::state::{GameState, TICK};
+use crate::tetris::{self, piece, Cell};
+
+use sfml::graphics::*;
+use sfml::system::Vector2f;
+
+// The part of the window the board is drawn in
+const BOARD: (f32, f32) = (240.0, 480.0);
+// Where the next piece is shown
+const PREVIEW: (f32, f32) = (312.0, 48.0);
+const NEXT_LABEL: (f32, f32) = (312.0, 22.0);
+// Where the rest of the queue is shown, two rows of three
+const QUEUE: (f32, f32) = (276.0, 180.0);
+// Where the held piece is shown, smaller than the next one to fit
+const HOLD: (f32, f32) = (276.0, 328.0);
+const HOLD_SIZE: f32 = 16.0;
+const HOLD_LABEL: (f32, f32) = (276.0, 304.0);
+// Where the score, level, lines and time are shown, a name and a value under it for each
+const STATS: (f32, f32) = (368.0, 304.0);
+const STATS_SPACING: f32 = 40.0;
+// Where watching a replay is shown, under what's held
+const REPLAY: (f32, f32) = (276.0, 416.0);
+// Where the last clear is shown, at the bottom of the side panel
+// Small enough for the longest, T-Spin Mini Double Perfect Clear, to fit
+const CLEAR: (f32, f32) = (276.0, 460.0);
+const CLEAR_TEXT: u32 = 10;
+
+const LABEL_TEXT: u32 = 16;
+const SMALL_TEXT: u32 = 12;
+const LABEL_COLOR: Color = Color::rgb(160, 160, 160);
+
+// A game being played, or a replay being watched
+pub struct Playing {
+    state: GameState,
+    repeat: input::Repeat,
+    actions: Vec<Action>, // What to give the game next tick
+    recording: Option<Replay>, // None once it's saved, or when watching
+    end: Option<u64>, // When the replay being watched ends
+
+    // Watching a replay
+    paused: bool,
+    steps: u32, // Ticks to play while paused
+}
+impl Playing {
+    // Start a game with the rules picked
+    // or watch the replay if there is one
+    pub fn new(game: &Game) -> Playing {
+        let state = game.new_state();
+        let recording = match game.playback {
+            Some(_) => None,
+            None => Some(Replay::new(state.get_seed(), game.rules, &game.pieces_path, game.pieces.get_hash())),
+        };
+        Playing{
+            state,
+            repeat: input::Repeat::new(game.timing),
+            actions: vec![],
+            recording,
+            end: game.playback.as_ref().map(|x| x.get_end()),
+            paused: false,
+            steps: 0,
+        }
+    }
+
+    // Save the game so far so it can be watched again
+    fn save_recording(&mut self, game: &Game) {
+        if let Some(mut recording) = self.recording.take() {
+            recording.finish(self.state.get_ticks());
+            game.save_replay(&recording);
+        }
+    }
+}
+impl Screen for Playing {
+    // Moving and soft dropping repeat while held
+    fn event(&mut self, _game: &mut Game, event: &ActionEvent) -> Change {
+        self.actions.extend(self.repeat.event(event));
+        if !event.pressed {
+            return Change::None
+        }
+        match (event.action, self.end) {
+            (Action::RotateLeft | Action::RotateRight | Action::HardDrop | Action::Hold, _) => self.actions.push(event.action),
+            (Action::Quit | Action::Pause | Action::LostFocus, None) => return Change::Push(Box::new(Pause::new())),
+            (Action::Quit, Some(_)) => return Change::Pop,
+            (Action::Pause, Some(_)) => self.paused = !self.paused,
+            (Action::LostFocus, Some(_)) => self.paused = true,
+            (Action::Step, Some(_)) => {self.paused = true; self.steps += 1},
+            _ => (),
+        }
+        Change::None
+    }
+
+    fn tick(&mut self, game: &mut Game) -> Change {
+        let mut actions = std::mem::take(&mut self.actions);
+        actions.extend(self.repeat.tick(self.state.get_gravity()));
+
+        match &game.playback {
+            Some(replay) => {
+                // Fast forward plays a few ticks for every one
+                let ticks = if self.paused {
+                    std::mem::take(&mut self.steps)
+                } else if *game.input.get_action(Action::FastForward) {
+                    4
+                } else {
+                    1
+                };
+                for _ in 0..ticks {
+                    if self.state.get_ticks() < replay.get_end() {
+                        self.state.step(replay.get_actions(self.state.get_ticks()));
+                    }
+                }
+            },
+            None => {
+                if let Some(recording) = &mut self.recording {
+                    recording.record(self.state.get_ticks(), &actions);
+                }
+                self.state.step(&actions);
+                if self.state.is_lost() {
+                    self.save_recording(game);
+                    let place = game.save_score(&self.state);
+                    let over = GameOver::new(&self.state, place, game.scores.get_top(scores::TOP));
+                    return Change::Replace(Box::new(over))
+                }
+            },
+        }
+        Change::None
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        draw_game(window, assets, &self.state);
+        if let Some(end) = self.end {
+            let status = if self.state.get_ticks() >= end {
+                "REPLAY OVER"
+            } else if self.paused {
+                "PAUSED"
+            } else {
+                "REPLAY"
+            };
+            super::draw_text(window, assets, status, REPLAY, SMALL_TEXT, Color::WHITE);
+        }
+    }
+
+    fn resume(&mut self, game: &mut Game) {
+        self.repeat = input::Repeat::new(game.timing);
+        self.actions.clear();
+    }
+
+    fn close(&mut self, game: &mut Game) {
+        self.save_recording(game);
+    }
+}
+
+// The board, the piece, the queue and what's held
+pub fn draw_game(window: &mut RenderWindow, assets: &Assets, state: &GameState) {
+    window.draw(&assets.get_background());
+    let board = draw_board(window, assets, state.get_tetris());
+
+    let tetris = state.get_tetris();
+    let mut bit = RectangleShape::with_texture(assets.get_bit(state.get_piece().get_color()));
+    bit.set_size(Vector2f::new(board.size, board.size));
+
+    // Draw where the piece will land, see-through
+    bit.set_fill_color(Color::rgba(255, 255, 255, 80));
+    for piece_bit in state.get_piece().get_ghost_pos(tetris) {
+        board.draw_bit(window, &mut bit, piece_bit);
+    }
+    bit.set_fill_color(Color::WHITE);
+
+    for piece_bit in state.get_piece().get_bits_pos() {
+        board.draw_bit(window, &mut bit, piece_bit);
+    }
+    bit.set_size(Vector2f::new(24.0, 24.0));
+
+    // Draw the queue, the next piece bigger than the rest
+    for (n, next_piece) in state.get_queue().iter().enumerate() {
+        bit.set_texture(assets.get_bit(next_piece.get_color()), false);
+        match n {
+            0 => draw_piece(window, &mut bit, next_piece, PREVIEW, 24.0),
+            n => {
+                let slot = ((n - 1) % 3, (n - 1) / 3);
+                let origin = (QUEUE.0 + slot.0 as f32 * 60.0, QUEUE.1 + slot.1 as f32 * 60.0);
+                draw_piece(window, &mut bit, next_piece, origin, 12.0)
+            },
+        }
+    }
+
+    if let Some(hold) = state.get_hold() {
+        bit.set_texture(assets.get_bit(hold.get_color()), false);
+        draw_piece(window, &mut bit, hold, HOLD, HOLD_SIZE);
+    }
+
+    draw_hud(window, assets, state);
+}
+
+// The labels and numbers in the side panel
+fn draw_hud(window: &mut RenderWindow, assets: &Assets, state: &GameState) {
+    super::draw_text(window, assets, "NEXT", NEXT_LABEL, LABEL_TEXT, Color::WHITE);
+    super::draw_text(window, assets, "HOLD", HOLD_LABEL, LABEL_TEXT, Color::WHITE);
+
+    let seconds = (TICK * state.get_ticks() as u32).as_secs();
+    let stats = [
+        ("SCORE", state.get_score().to_string()),
+        ("LEVEL", state.get_level().to_string()),
+        ("LINES", state.get_lines().to_string()),
+        ("TIME", format!("{}:{:02}", seconds / 60, seconds % 60)),
+    ];
+    for (n, (name, value)) in stats.iter().enumerate() {
+        let y = STATS.1 + n as f32 * STATS_SPACING;
+        super::draw_text(window, assets, name, (STATS.0, y), SMALL_TEXT, LABEL_COLOR);
+        super::draw_text(window, assets, value, (STATS.0, y + 14.0), LABEL_TEXT, Color::WHITE);
+    }
+
+    if let Some(clear) = state.get_clear().and_then(|x| x.get_message()) {
+        super::draw_text(window, assets, &clear, CLEAR, CLEAR_TEXT, Color::WHITE);
+    }
+}
+
+// Draw every bit on the grid, over the background
+pub fn draw_board(window: &mut RenderWindow, assets: &Assets, tetris: &tetris::Tetris) -> Board {
+    let board = Board::new(tetris);
+
+    // background.png only has room for a 10x20 board
+    // draw any other size over it
+    if !board.is_standard() {
+        let mut cover = RectangleShape::with_size(Vector2f::new(BOARD.0, BOARD.1));
+        cover.set_fill_color(Color::rgb(0, 0, 0));
+        window.draw(&cover);
+    }
+
+    let mut bit = RectangleShape::with_texture(assets.get_grey());
+    bit.set_size(Vector2f::new(board.size, board.size));
+    for y in 0..tetris.get_height() as i8 {
+        for x in 0..tetris.get_width() as i8 {
+            let pos = piece::Pos(y, x);
+            match tetris.get_grid_pos(pos) {
+                Cell::Filled{kind: _, color} => {
+                    bit.set_texture(assets.get_bit(color), false);
+                    board.draw_bit(window, &mut bit, pos);
+                },
+                Cell::Garbage => {
+                    bit.set_texture(assets.get_grey(), false);
+                    board.draw_bit(window, &mut bit, pos);
+                },
+                Cell::Empty => if !board.is_standard() {
+                    bit.set_texture(assets.get_grey(), false);
+                    bit.set_fill_color(Color::rgb(60, 60, 60));
+                    board.draw_bit(window, &mut bit, pos);
+                    bit.set_fill_color(Color::WHITE);
+                },
+            }
+        }
+    }
+    board
+}
+
+// Where the board is drawn and how big its bits are
+pub struct Board {
+    origin: (f32, f32),
+    size: f32,
+    width: usize,
+    height: usize,
+}
+impl Board {
+    // Fit the board into its part of the window
+    fn new(tetris: &tetris::Tetris) -> Board {
+        let width = tetris.get_width();
+        let height = tetris.get_height();
+        let size = (BOARD.0 / width as f32).min(BOARD.1 / height as f32).floor();
+        Board{
+            origin: ((BOARD.0 - size * width as f32) / 2.0, (BOARD.1 - size * height as f32) / 2.0),
+            size,
+            width,
+            height,
+        }
+    }
+
+    // If the board is the one drawn in background.png
+    fn is_standard(&self) -> bool {
+        self.width == 10 && self.height == 20
+    }
+
+    // Draw a bit at a Pos on the grid
+    // The hidden rows aren't drawn
+    fn draw_bit(&self, window: &mut RenderWindow, bit: &mut RectangleShape, pos: piece::Pos) {
+        if pos.0 < 0 || pos.1 < 0 {
+            return
+        }
+        bit.set_position((self.origin.0 + pos.1 as f32 * self.size, self.origin.1 + pos.0 as f32 * self.size));
+        window.draw(bit);
+    }
+}
+
+// Draw a piece on its own, away from the grid
+// centered in a box 5 bits wide with its top left at origin
+// Pieces too big for the box are drawn smaller
+pub fn draw_piece(window: &mut RenderWindow, bit: &mut RectangleShape, piece: &piece::Piece, origin: (f32, f32), size: f32) {
+    let bits: Vec<piece::Pos> = piece.get_bits_pos().into_iter().map(|x| x - piece.get_pos()).collect();
+    let top = bits.iter().map(|x| x.0).min().unwrap_or(0);
+    let left = bits.iter().map(|x| x.1).min().unwrap_or(0);
+    let height = (bits.iter().map(|x| x.0).max().unwrap_or(0) - top + 1) as f32;
+    let width = (bits.iter().map(|x| x.1).max().unwrap_or(0) - left + 1) as f32;
+
+    let boxed = size * 5.0;
+    let size = size.min(boxed / width.max(height));
+    let origin = (origin.0 + (boxed - width * size) / 2.0, origin.1 + (boxed - height * size) / 2.0);
+
+    let old_size = bit.size();
+    bit.set_size(Vector2f::new(size, size));
+    for piece_bit in bits {
+        let piece_bit = piece_bit - piece::Pos(top, left);
+        bit.set_position((origin.0 + (piece_bit.1 as f32) * size, origin.1 + (piece_bit.0 as f32) * size));
+        window.draw(bit);
+    }
+    bit.set_size(old_size);
+}
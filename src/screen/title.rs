@@ -0,0 +1,49 @@
+use crate::{Assets, Game};
+use crate::input::ActionEvent;
+use crate::screen::{Change, Choice, Menu, Screen};
+use crate::screen::high_scores::HighScores;
+use crate::screen::mode::ModeSelect;
+use crate::screen::options::Options;
+use crate::screen::playing::Playing;
+
+use sfml::graphics::*;
+
+// The first screen, going back from it quits
+pub struct Title {
+    menu: Menu,
+}
+impl Title {
+    pub fn new() -> Title {
+        Title{
+            menu: Menu::new(&["Play", "Mode", "Options", "High scores", "Quit"]),
+        }
+    }
+}
+impl Default for Title {
+    fn default() -> Title {
+        Title::new()
+    }
+}
+impl Screen for Title {
+    fn event(&mut self, game: &mut Game, event: &ActionEvent) -> Change {
+        match self.menu.event(event) {
+            Some(Choice::Select(0)) => Change::Push(Box::new(Playing::new(game))),
+            Some(Choice::Select(1)) => Change::Push(Box::new(ModeSelect::new(game))),
+            Some(Choice::Select(2)) => Change::Push(Box::new(Options::new(game))),
+            Some(Choice::Select(3)) => Change::Push(Box::new(HighScores::new(game))),
+            Some(Choice::Select(_)) | Some(Choice::Back) => Change::Quit,
+            _ => Change::None,
+        }
+    }
+
+    fn draw(&self, window: &mut RenderWindow, assets: &Assets) {
+        window.draw(&assets.get_background());
+        super::draw_shade(window);
+        super::draw_heading(window, assets, "Tetris");
+        self.menu.draw(window, assets);
+    }
+
+    fn is_waiting(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,461 @@
+use crate::tetris::{self, piece};
+use crate::tetris::randomizer::{Randomizer, Randomizers};
+use crate::tetris::scoring::{Clear, Scoring, ScoringRules};
+use crate::tetris::set::PieceSet;
+use crate::input::Action;
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Change this whenever the same seed, rules and actions
+// would play out differently, so old replays aren't played wrong
+pub const RULES_VERSION: u32 = 2;
+
+// How many times a second the game steps, no matter how fast it's drawn
+pub const TICKS: u32 = 60;
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / TICKS as u64);
+
+// How many cells a piece falls each tick at each level, starting at level 1
+// Levels past the end use the last one
+const GRAVITY: [f64; 19] = [
+    0.01667, 0.021, 0.027, 0.0353, 0.0469,
+    0.0636, 0.0879, 0.1237, 0.1775, 0.2598,
+    0.3878, 0.5906, 0.9181, 1.457, 2.3612,
+    3.9091, 6.6135, 11.4379, 20.0,
+];
+
+// Everything that can be chosen before a game starts
+#[derive(Clone, Copy, Debug)]
+pub struct Rules {
+    pub randomizer: Randomizers,
+    pub scoring: ScoringRules,
+    pub previews: usize, // How many upcoming pieces can be seen, 1 to 6
+    pub lock_delay: Duration, // How long a piece can sit on the ground before it locks
+    pub lock_resets: u32, // How many times moving or rotating can restart the lock delay
+    pub start_level: u32,
+    pub level_lines: u32, // How many lines it takes to go up a level
+    pub width: usize, // How many columns the board has
+    pub height: usize, // How many rows of the board can be seen
+    pub buffer: usize, // How many rows are hidden above those
+}
+impl Rules {
+    pub fn new() -> Rules {
+        Rules{
+            randomizer: Randomizers::Uniform,
+            scoring: ScoringRules::Guideline,
+            previews: 3,
+            lock_delay: Duration::from_millis(500),
+            lock_resets: 15,
+            start_level: 1,
+            level_lines: 10,
+            width: 10,
+            height: 20,
+            buffer: 20,
+        }
+    }
+
+    // Make sure the rules can be played with
+    // A board too big doesn't fit in a Pos, one too small can't be drawn
+    pub fn check(&self) -> Result<(), String> {
+        if !(4..=40).contains(&self.width) {
+            return Err("Width has to be 4 to 40".to_string())
+        }
+        if !(4..=60).contains(&self.height) {
+            return Err("Height has to be 4 to 60".to_string())
+        }
+        if self.buffer > 40 {
+            return Err("Hidden rows have to be 0 to 40".to_string())
+        }
+        if !(1..=6).contains(&self.previews) {
+            return Err("Previews have to be 1 to 6".to_string())
+        }
+        if self.level_lines == 0 {
+            return Err("Lines per level has to be at least 1".to_string())
+        }
+        Ok(())
+    }
+
+    // A short name for the rules, so scores from different ones can be told apart
+    pub fn get_mode(&self) -> String {
+        format!("{} {} {}x{}", self.scoring.get_name(), self.randomizer.get_name(), self.width, self.height)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::new()
+    }
+}
+
+// All of the rules of the game, without a window
+// Feed it the actions for a step and how much time has passed
+// The same seed and actions always play out the same game
+pub struct GameState {
+    rules: Rules,
+    seed: u64,
+    rng: ChaCha8Rng, // Every random choice comes from here, it deals the same on every version of rand
+    randomizer: Box<dyn Randomizer>,
+    pieces: Rc<PieceSet>, // What the randomizer picks from
+    tetris: tetris::Tetris,
+    piece: piece::Piece,
+    queue: VecDeque<piece::Piece>, // The upcoming pieces, next first
+    hold: Option<piece::Piece>,
+    held: bool, // Hold can only be used once per piece
+    scoring: Scoring,
+    clear: Option<Clear>, // The last lock that cleared lines or spun
+    level: u32,
+    lines: u32, // How many lines have been cleared
+    placed: u64, // How many pieces have been locked
+    kinds: Vec<u32>, // How many of each kind of piece have been locked
+    clears: Vec<(Clear, u32)>, // How many times each clear worth a message has happened, first seen first
+    fall: f64, // How far gravity has pulled the piece since it last moved down
+    lock_timer: Duration, // Time the piece has been on the ground
+    lock_resets: u32, // Times the lock delay has been restarted for this piece
+    lowest: i8, // The lowest row the piece has reached
+    ticks: u64, // How many times the game has stepped
+    lost: bool,
+}
+impl GameState {
+    pub fn new(seed: u64, rules: Rules, pieces: Rc<PieceSet>) -> GameState {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut randomizer = rules.randomizer.create();
+        let tetris = tetris::Tetris::new(rules.width, rules.height, rules.buffer);
+        let piece = GameState::random_piece(&mut rng, randomizer.as_mut(), &pieces, &tetris);
+        let mut queue = VecDeque::new();
+        for _ in 0..rules.previews.clamp(1, 6) {
+            queue.push_back(GameState::random_piece(&mut rng, randomizer.as_mut(), &pieces, &tetris));
+        }
+        let lowest = piece.get_pos().0;
+        GameState{
+            rules,
+            seed,
+            rng,
+            randomizer,
+            kinds: vec![0; pieces.len()],
+            pieces,
+            tetris,
+            piece,
+            queue,
+            hold: None,
+            held: false,
+            scoring: Scoring::new(rules.scoring),
+            clear: None,
+            level: rules.start_level.max(1),
+            lines: 0,
+            placed: 0,
+            clears: vec![],
+            fall: 0.0,
+            lock_timer: Duration::ZERO,
+            lock_resets: 0,
+            lowest,
+            ticks: 0,
+            lost: false,
+        }
+    }
+
+    // Ask the randomizer for the next piece
+    // and pick a color for it if it doesn't have one
+    fn random_piece(rng: &mut ChaCha8Rng, randomizer: &mut dyn Randomizer, pieces: &PieceSet, tetris: &tetris::Tetris) -> piece::Piece {
+        let kind = randomizer.next(rng, pieces);
+        let color = match pieces.get(kind).get_color() {
+            Some(color) => color,
+            None => rng.gen_range(0..piece::COLORS),
+        };
+        GameState::create_piece(kind, color, pieces, tetris)
+    }
+
+    // Make a piece where it spawns on this board
+    fn create_piece(kind: usize, color: usize, pieces: &PieceSet, tetris: &tetris::Tetris) -> piece::Piece {
+        let mut piece = piece::Piece::new(kind, pieces.get(kind), color);
+        piece.set_pos(piece.get_pos() + tetris.spawn_offset());
+        piece
+    }
+
+    pub fn get_pieces(&self) -> &PieceSet {
+        &self.pieces
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_score(&self) -> u64 {
+        self.scoring.get_score()
+    }
+
+    pub fn get_scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
+    pub fn get_clear(&self) -> Option<Clear> {
+        self.clear
+    }
+
+    pub fn get_level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn get_lines(&self) -> u32 {
+        self.lines
+    }
+
+    // How many cells a piece falls each tick
+    pub fn get_gravity(&self) -> f64 {
+        GRAVITY[(self.level.max(1) as usize - 1).min(GRAVITY.len() - 1)]
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn get_placed(&self) -> u64 {
+        self.placed
+    }
+
+    // How many of each kind of piece have been locked, by kind
+    pub fn get_kinds(&self) -> &[u32] {
+        &self.kinds
+    }
+
+    pub fn get_clears(&self) -> &[(Clear, u32)] {
+        &self.clears
+    }
+
+    pub fn get_tetris(&self) -> &tetris::Tetris {
+        &self.tetris
+    }
+
+    pub fn get_piece(&self) -> &piece::Piece {
+        &self.piece
+    }
+
+    pub fn get_queue(&self) -> &VecDeque<piece::Piece> {
+        &self.queue
+    }
+
+    pub fn get_hold(&self) -> Option<&piece::Piece> {
+        self.hold.as_ref()
+    }
+
+    // Take the next piece out of the queue
+    // and refill the queue behind it
+    fn next_piece(&mut self) -> piece::Piece {
+        let next = GameState::random_piece(&mut self.rng, self.randomizer.as_mut(), &self.pieces, &self.tetris);
+        self.queue.push_back(next);
+        self.queue.pop_front().expect("Queue is empty")
+    }
+
+    // Put a piece into play
+    // If there's no room for it, the game is lost
+    fn spawn(&mut self, piece: piece::Piece) {
+        self.piece = piece;
+        if !self.piece.is_free(&self.tetris) {
+            self.lost = true;
+        }
+        self.fall = 0.0;
+        self.lock_timer = Duration::ZERO;
+        self.lock_resets = 0;
+        self.lowest = self.piece.get_pos().0;
+    }
+
+    // The piece has moved or rotated
+    // Restart the lock delay if there are resets left
+    fn moved(&mut self) {
+        if self.piece.get_pos().0 > self.lowest {
+            // Reaching a new row gives all the resets back
+            self.lowest = self.piece.get_pos().0;
+            self.lock_resets = 0;
+            self.lock_timer = Duration::ZERO;
+        } else if self.lock_timer > Duration::ZERO && self.lock_resets < self.rules.lock_resets {
+            self.lock_resets += 1;
+            self.lock_timer = Duration::ZERO;
+        }
+    }
+
+    // Swap the piece with the one being held
+    // If nothing is held yet, the next piece comes in
+    // A piece that was dropped this tick is already placed, it can't be held
+    fn hold(&mut self) {
+        if self.held || !self.piece.is_alive() {
+            return
+        }
+        self.held = true;
+
+        // Start the held piece over from the top
+        let held = GameState::create_piece(self.piece.get_kind(), self.piece.get_color(), &self.pieces, &self.tetris);
+        let piece = match self.hold.replace(held) {
+            Some(piece) => piece,
+            None => self.next_piece(),
+        };
+        self.spawn(piece);
+    }
+
+    // Once the game is lost, step does nothing
+    pub fn is_lost(&self) -> bool {
+        self.lost
+    }
+
+    // Advance the game by one tick
+    // Every action given is applied once
+    pub fn step(&mut self, inputs: &[Action]) {
+        if self.lost {
+            return
+        }
+        self.ticks += 1;
+
+        for action in inputs {
+            let moved = match action {
+                Action::Left => self.piece.r#move(piece::Dir::Left, &self.tetris),
+                Action::Right => self.piece.r#move(piece::Dir::Right, &self.tetris),
+                Action::Down => {
+                    let moved = self.piece.r#move(piece::Dir::Down, &self.tetris);
+                    if moved {
+                        self.scoring.soft_drop(1, self.level);
+                    }
+                    moved
+                },
+                Action::RotateLeft => self.piece.rotate(piece::Rotate::Left, &self.tetris),
+                Action::RotateRight => self.piece.rotate(piece::Rotate::Right, &self.tetris),
+                Action::HardDrop => {
+                    let rows = self.piece.hard_drop(&self.tetris);
+                    self.scoring.hard_drop(rows as u32, self.level);
+                    false
+                },
+                Action::Hold => {self.hold(); false},
+                _ => false,
+            };
+            if moved {
+                self.moved();
+            }
+        }
+
+        // Gravity
+        // Past 1G the piece falls more than one cell at once
+        self.fall += self.get_gravity();
+        while self.fall >= 1.0 {
+            self.fall -= 1.0;
+            if self.piece.r#move(piece::Dir::Down, &self.tetris) {
+                self.moved();
+            } else {
+                self.fall = 0.0;
+            }
+        }
+
+        // Lock the piece once it has been on the ground long enough
+        // Only count time spent on the ground, so it can't lock in mid-air
+        // Being kicked up doesn't restart it, only a reset or a new lowest row does
+        if self.piece.is_alive() && self.piece.is_grounded(&self.tetris) {
+            self.lock_timer += TICK;
+            if self.lock_timer >= self.rules.lock_delay {
+                self.piece.lock();
+            }
+        }
+
+        // Check if piece is dead
+        if !self.piece.is_alive() {
+            // See if it spun in before it becomes part of the grid
+            let spin = self.piece.get_spin(&self.tetris);
+            if !self.piece.apply_to_grid(&mut self.tetris) {
+                // Game has been lost
+                self.lost = true;
+                return
+            }
+            self.placed += 1;
+            self.kinds[self.piece.get_kind()] += 1;
+
+            // Check if there are full lines
+            let lines = self.tetris.check_lines();
+            let clear = Clear{
+                lines,
+                spin,
+                perfect: lines > 0 && self.tetris.is_empty(),
+            };
+            self.scoring.lock(clear, self.level);
+            if clear.get_message().is_some() {
+                self.clear = Some(clear);
+                match self.clears.iter_mut().find(|x| x.0 == clear) {
+                    Some(count) => count.1 += 1,
+                    None => self.clears.push((clear, 1)),
+                }
+            }
+            self.lines += lines as u32;
+            self.level = self.level.max(1 + self.lines / self.rules.level_lines.max(1));
+
+            let piece = self.next_piece();
+            self.spawn(piece);
+            self.held = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::randomizer::Randomizers;
+
+    fn new_state(seed: u64) -> GameState {
+        let mut rules = Rules::new();
+        rules.randomizer = Randomizers::Bag;
+        GameState::new(seed, rules, Rc::new(PieceSet::standard()))
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same() {
+        let (mut a, mut b) = (new_state(9), new_state(9));
+        for _ in 0..5000 {
+            assert_eq!(a.get_piece().get_color(), b.get_piece().get_color());
+            assert_eq!(a.get_queue()[0].get_color(), b.get_queue()[0].get_color());
+            a.step(&[]);
+            b.step(&[]);
+            assert_eq!(a.get_tetris().return_grid(), b.get_tetris().return_grid());
+        }
+        assert!(a.get_placed() > 0);
+    }
+
+    #[test]
+    fn hold_only_once_per_piece() {
+        let mut state = new_state(3);
+        let first = state.get_piece().get_kind();
+        let next = state.get_queue()[0].get_kind();
+        state.step(&[Action::Hold]);
+        assert_eq!(state.get_hold().map(|x| x.get_kind()), Some(first));
+        assert_eq!(state.get_piece().get_kind(), next);
+        state.step(&[Action::Hold]);
+        assert_eq!(state.get_piece().get_kind(), next);
+    }
+
+    #[test]
+    fn hold_after_hard_drop_keeps_the_drop() {
+        let mut state = new_state(1);
+        state.step(&[Action::HardDrop, Action::Hold]);
+        assert_eq!(state.get_placed(), 1);
+        assert!(state.get_tetris().return_grid().iter().flatten().any(|x| *x != tetris::Cell::Empty));
+        assert!(state.get_hold().is_none());
+    }
+
+    #[test]
+    fn stacking_in_the_middle_loses() {
+        let mut state = new_state(4);
+        for _ in 0..100 {
+            state.step(&[Action::HardDrop]);
+        }
+        assert!(state.is_lost());
+        let placed = state.get_placed();
+        state.step(&[Action::HardDrop]);
+        assert_eq!(state.get_placed(), placed);
+    }
+
+    #[test]
+    fn rules_out_of_range_are_rejected() {
+        assert!(Rules::new().check().is_ok());
+        let mut rules = Rules::new();
+        rules.width = 3;
+        assert!(rules.check().is_err());
+        let mut rules = Rules::new();
+        rules.previews = 7;
+        assert!(rules.check().is_err());
+    }
+}
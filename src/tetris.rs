@@ -1,65 +1,109 @@
 pub mod piece;
+pub mod randomizer;
+pub mod scoring;
+pub mod set;
 
-#[derive(Debug)]
+// How wide the board the pieces file is written for is
+const SPAWN_WIDTH: usize = 10;
+
+// What is in a spot on the grid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Cell {
+    Empty,
+    Filled{kind: usize, color: usize}, // Left by a piece that locked
+    Garbage, // Put there by the game, not by a piece
+}
+
+#[derive(Clone, Debug)]
 pub struct Tetris {
-    grid: Vec<Vec<i8>>
+    grid: Vec<Vec<Cell>>, // The hidden rows first, then the visible ones
+    width: usize,
+    height: usize, // How many rows can be seen
+    buffer: usize, // How many rows are hidden above the ones that can be seen
 }
 impl Tetris {
-    pub fn new() -> Tetris {
+    // Rows above the visible ones have negative positions
+    pub fn new(width: usize, height: usize, buffer: usize) -> Tetris {
         Tetris{
-            grid: vec!(vec!(0; 10); 20),
+            grid: vec!(vec!(Cell::Empty; width); buffer + height),
+            width,
+            height,
+            buffer,
         }
     }
 
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_buffer(&self) -> usize {
+        self.buffer
+    }
+
     // Return the grid
-    pub fn return_grid(&self) -> &Vec<Vec<i8>> {
+    pub fn return_grid(&self) -> &Vec<Vec<Cell>> {
         &self.grid
     }
 
+    // Pieces spawn where the pieces file says on a board
+    // as wide as it was written for, and move to stay in the middle
+    pub fn spawn_offset(&self) -> piece::Pos {
+        piece::Pos(0, ((self.width as isize - SPAWN_WIDTH as isize) / 2) as i8)
+    }
+
+    // Where a Pos is in the grid, if it's in the grid at all
+    fn index(&self, pos: piece::Pos) -> Option<(usize, usize)> {
+        let row = pos.0 as isize + self.buffer as isize;
+        if row < 0 || row >= self.grid.len() as isize || pos.1 < 0 || pos.1 as usize >= self.width {
+            None
+        } else {
+            Some((row as usize, pos.1 as usize))
+        }
+    }
+
     // Set the grid at a Pos
-    pub fn set_grid(&mut self, pos: piece::Pos, value: i8) {
-        if pos.0 < 0 || pos.1 < 0 {
-            // Lose game
-        } else if self.get_grid_pos(pos) != value {
-            self.grid[pos.0 as usize][pos.1 as usize] = value
+    pub fn set_grid(&mut self, pos: piece::Pos, value: Cell) {
+        if let Some((y, x)) = self.index(pos) {
+            self.grid[y][x] = value
+        }
+    }
+
+    // If a bit can be at a Pos
+    // Above the hidden rows is empty, the walls and floor are not
+    pub fn is_free(&self, pos: piece::Pos) -> bool {
+        if pos.1 < 0 || pos.1 as usize >= self.width || pos.0 as isize >= self.height as isize {
+            return false
         }
+        self.get_grid_pos(pos) == Cell::Empty
     }
 
     // Check each row of the grid
     // If one is full, remove it and drop
     // the rest of the grid down
     pub fn check_lines(&mut self) -> u8 {
-        // While there are full lines, continue to iterate
-        let mut row = 19;
-        let mut total = 0;
-        while row > 0 {
-            let mut c = 0;
-            for x in self.grid.get(row as usize).expect("Out of bounds") {
-                if *x == 1 {
-                    c += 1;
-                }
-            }
+        let rows = self.grid.len();
+        self.grid.retain(|row| row.contains(&Cell::Empty));
 
-            // If the line is full remove it and
-            // move everything down
-
-            if c == 10 {
-                self.grid.remove(row as usize);
-                self.grid.insert(0, vec![0; 10]);
-                total += 1;
-                continue;
-            }
-
-            row -= 1;
+        let total = rows - self.grid.len();
+        for _ in 0..total {
+            self.grid.insert(0, vec![Cell::Empty; self.width]);
         }
-        return total
+        total as u8
     }
 
-    pub fn get_grid_pos(&self, pos: piece::Pos) -> i8 {
-        match self.grid.get(pos.0 as usize) {
-            None => return 0,
-            _ => return *self.grid.get(pos.0 as usize).unwrap()
-            .get(pos.1 as usize).unwrap_or(&0)
+    // If there's nothing left on the grid
+    pub fn is_empty(&self) -> bool {
+        self.grid.iter().flatten().all(|x| *x == Cell::Empty)
+    }
+
+    pub fn get_grid_pos(&self, pos: piece::Pos) -> Cell {
+        match self.index(pos) {
+            Some((y, x)) => self.grid[y][x],
+            None => Cell::Empty,
         }
     }
-}
\ No newline at end of file
+}
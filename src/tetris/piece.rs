@@ -1,6 +1,7 @@
-use rand::Rng;
 use std::ops::{AddAssign, Sub, Add};
-use crate::tetris::Tetris;
+use crate::tetris::{Tetris, Cell};
+use crate::tetris::set::PieceDef;
+use crate::tetris::scoring::Spin;
 
 #[derive(Copy)]
 #[derive(Clone)]
@@ -36,11 +37,31 @@ impl Sub for Pos {
     }
 }
 
+// How many colors a piece can be
+pub const COLORS: usize = 3;
+
 const NONE: Pos = Pos(0,0);
 const DOWN: Pos = Pos(1,0);
 const LEFT: Pos = Pos(0,-1);
 const RIGHT: Pos = Pos(0,1);
 
+// Super Rotation System wall kicks
+// Rotation states are 0 (spawn), R, 2, L
+// Where a rotation's kicks are in a kick table
+pub fn kick_index(from: usize, to: usize) -> usize {
+    match (from, to) {
+        (0, 1) => 0,
+        (1, 0) => 1,
+        (1, 2) => 2,
+        (2, 1) => 3,
+        (2, 3) => 4,
+        (3, 2) => 5,
+        (3, 0) => 6,
+        (0, 3) => 7,
+        _ => panic!("No kicks from {} to {}", from, to),
+    }
+}
+
 #[derive(PartialEq)]
 pub enum Rotate {
     Left,
@@ -65,128 +86,82 @@ impl Dir {
     }
 }
 
-pub enum Pieces {
-    NormalL,
-    ReverseL,
-    Cube,
-    Tee,
-    Diag,
-    ReverseDiag,
-    Straight,
-}
-impl Pieces {
-    pub fn random() -> Pieces {
-        use Pieces::*;
-        let mut rand = rand::thread_rng();
-        match rand.gen_range(0..7) {
-            0 => NormalL,
-            1 => ReverseL,
-            2 => Cube,
-            3 => Tee,
-            4 => Diag,
-            5 => ReverseDiag,
-            6 => Straight,
-            _ => NormalL,
-        }
-    }
-
-    fn get(&self) -> Vec<Vec<i8>> {
-        let l: Vec<Vec<i8>> = vec![
-            // Outer most ring: top, right, bottom, left
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            // Inner rign: top, right, bottom, left
-            vec![0,1,0, 0, 0,1,1, 0],
-            // Center ring.  Is used as the center of rotation
-            vec![1],
-        ];
-        let r_l: Vec<Vec<i8>> = vec![
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![0,1,0, 0, 1,1,0, 0],
-            vec![1],
-        ];
-        let cube: Vec<Vec<i8>> = vec![
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![0,1,1, 1, 0,0,0, 0],
-            vec![1],
-        ];
-        let tee: Vec<Vec<i8>> = vec![
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![0,1,0, 1, 0,0,0, 1],
-            vec![1],
-        ];
-        let diag: Vec<Vec<i8>> = vec![
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![1,1,0, 1, 0,0,0, 0],
-            vec![1],
-        ];
-        let r_diag: Vec<Vec<i8>> = vec![
-            vec![0,0,0,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![0,1,1, 0, 0,0,0, 1],
-            vec![1],
-        ];
-        let straight: Vec<Vec<i8>> = vec![
-            vec![0,0,1,0,0, 0,0,0, 0,0,0,0,0, 0,0,0],
-            vec![0,1,0, 0, 0,1,0, 0],
-            vec![1],
-        ];
-
-        use Pieces::*;
-
-        match &self {
-            NormalL => l,
-            ReverseL => r_l,
-            Cube => cube,
-            Tee => tee,
-            Diag => diag,
-            ReverseDiag => r_diag,
-            Straight => straight,
-        }
-    }
-}
-
 pub struct Piece {
-    area: Vec<Vec<i8>>, // A static 3x3 area
+    kind: usize, // Which piece of the set this is
+    states: Vec<Vec<Pos>>, // Every rotation state, as bits from the position
+    kicks: Vec<Vec<Pos>>, // The kicks for each rotation
     position: Pos,
+    rotation: usize, // Which rotation state the piece is in, 0 is how it spawns
+    kick: Option<usize>, // Which kick the last rotation used
+    rotated: bool, // If the last thing the piece did was rotate
+    spin: bool, // If the piece can T-spin
+    color: usize,
     alive: bool,
 }
 impl Piece {
-    // Return a random piece
-    // out of the enum Pieces
-    pub fn random(pos: Pos) -> Piece {
-        let piece = Pieces::random();
+    pub fn new(kind: usize, def: &PieceDef, color: usize) -> Piece {
         Piece{
-            area: piece.get(),
-            position: pos,
+            kind,
+            states: def.get_states().clone(),
+            kicks: def.get_kicks().clone(),
+            position: def.get_spawn(),
+            rotation: 0,
+            kick: None,
+            rotated: false,
+            spin: def.can_spin(),
+            color,
             alive: true,
         }
     }
 
-    // Get area as a 2d array
-    fn get_area(&self) -> [[&i8; 5]; 5] {
-        let mut array = [[&0; 5]; 5];
-        
-        for sections in &self.area {
-            let len = sections.len();
-            let mut y = [0; 16];
-            let mut x = [0; 16];
-
-            if len == 16 {
-                y = [0,0,0,0,0,1,2,3,4,4,4,4,4,3,2,1];
-                x = [0,1,2,3,4,4,4,4,4,3,2,1,0,0,0,0];
-            } else if len == 8 {
-                y = [1,1,1,2,3,3,3,2,0,0,0,0,0,0,0,0];
-                x = [1,2,3,3,3,2,1,1,0,0,0,0,0,0,0,0];
-            } else if len == 1 {
-                y = [2,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0];
-                x = [2,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0];                
-            }
+    pub fn get_kind(&self) -> usize {
+        self.kind
+    }
 
-            for c in 0..len {
-                array[y[c]][x[c]] = sections.get(c).expect("Invalid");
+    pub fn get_rotation(&self) -> usize {
+        self.rotation
+    }
+
+    // The kick the last rotation used, 0 is no kick at all
+    pub fn get_kick(&self) -> Option<usize> {
+        self.kick
+    }
+
+    pub fn get_color(&self) -> usize {
+        self.color
+    }
+
+    // If the piece spun into where it is, by the 3 corner rule
+    // 3 of the corners around the middle have to be filled
+    // It's a mini unless both corners in front are filled,
+    // or the last kick moved it the farthest
+    pub fn get_spin(&self, grid: &Tetris) -> Spin {
+        if !self.spin || !self.rotated {
+            return Spin::None
+        }
+
+        let bits = &self.states[self.rotation];
+        let mut filled = 0;
+        let mut front = 0;
+        for corner in [Pos(0,0), Pos(0,2), Pos(2,0), Pos(2,2)] {
+            if grid.is_free(self.position + corner) {
+                continue
+            }
+            filled += 1;
+            // The corners in front are between two bits of the piece
+            let sides = [Pos(corner.0, 1), Pos(1, corner.1)];
+            if sides.iter().all(|side| bits.contains(side)) {
+                front += 1;
             }
         }
-        return array
 
+        if filled < 3 {
+            Spin::None
+        } else if front == 2 || self.kick == Some(4) {
+            Spin::Full
+        } else {
+            Spin::Mini
+        }
     }
 
     // Using a grid and a direction;
@@ -194,113 +169,112 @@ impl Piece {
     // something in the grid
     // If so, return Err
     // otherwise return Ok
-    fn hit_detect(&mut self, dir: &Dir, grid: &Tetris) -> Result<(), ()> {        
-        for pos in self.get_bits_pos() {
-            let new_pos = pos + dir.get();
-
-            if self.is_alive() {
-                // Detect blocks and kill only if Dir is down
-                if grid.get_grid_pos(new_pos) == 1 && *dir == Dir::Down {
-                    self.kill();
-                    return Err(())
-                } else if grid.get_grid_pos(new_pos) == 1 {
-                    return Err(())
-                } else if new_pos.0 > 19 { // Detect bottom and kill
-                    self.kill();
-                    return Err(())
-                } else if new_pos.1 > 9 || new_pos.1 < 0 { // Detect sides
-                    return Err(())
-                }
-            } else {
-                return Err(())
-            }
+    // Landing doesn't kill the piece, locking does
+    fn hit_detect(&self, dir: &Dir, grid: &Tetris) -> Result<(), ()> {
+        if self.is_alive() && self.fits(dir.get(), grid) {
+            Ok(())
+        } else {
+            Err(())
         }
-        Ok(())
     }
 
-    // Return false on kill
-    pub fn apply_to_grid(&mut self, grid: &mut Tetris) -> bool {
+    // See if the piece would fit in the grid
+    // if it was moved by offset
+    fn fits(&self, offset: Pos, grid: &Tetris) -> bool {
         for pos in self.get_bits_pos() {
-            if pos.0 < 0 {
-                self.kill();
+            let new_pos = pos + offset;
+            if !grid.is_free(new_pos) {
                 return false
-            } else {
-                grid.set_grid(pos, 1)
             }
         }
-        return true
+        true
     }
 
-    fn apply_dir(&mut self, dir: &Dir) {
-        self.position += dir.get();
+    // How many rows the piece can fall before it lands
+    pub fn drop_distance(&self, grid: &Tetris) -> i8 {
+        let mut distance = 0;
+        while self.fits(Pos(distance + 1, 0), grid) {
+            distance += 1;
+        }
+        distance
     }
 
-    pub fn get_bits_pos(&self) -> Vec<Pos> {
-        let mut piece_pos_vec = vec![];
-
-        let mut y = -1;
-        for posy in self.get_area() {
-            let mut x = -1;
-            for posx in posy {
-                x += 1;
-                if posx == &1 {
-                    piece_pos_vec.push(self.get_pos() + Pos(y, x))
-                }
-            }
-            y += 1;
-        }
+    // Where the bits would be if the piece was dropped
+    pub fn get_ghost_pos(&self, grid: &Tetris) -> Vec<Pos> {
+        let distance = Pos(self.drop_distance(grid), 0);
+        self.get_bits_pos().into_iter().map(|pos| pos + distance).collect()
+    }
 
-        return piece_pos_vec
+    // Drop the piece to where it lands and kill it
+    // Return how many rows it fell
+    pub fn hard_drop(&mut self, grid: &Tetris) -> i8 {
+        if !self.is_alive() {
+            return 0
+        }
+        let distance = self.drop_distance(grid);
+        self.position += Pos(distance, 0);
+        if distance > 0 {
+            self.rotated = false;
+        }
+        self.kill();
+        distance
     }
 
-    pub fn rotate(&mut self, r: Rotate, tetris: &Tetris) {
-        let mut area = self.area.clone();
-
-        for section in &self.area {
-            let len = section.len();
-            let mut range: Vec<usize> = (0..len).collect();
-            if r == Rotate::Left {
-                let mut new = vec![];
-                for bit in range {
-                    new.insert(0, bit);
-                }
-                range = new;
-            }
+    // Return false if the game is lost
+    // That's when the whole piece is above the rows that can be seen
+    // or any of it is above the hidden rows
+    pub fn apply_to_grid(&mut self, grid: &mut Tetris) -> bool {
+        let bits = self.get_bits_pos();
+        self.kill();
 
-            for c in range {
-                let mut loop_num = 0;
-                let mut loop_c = 4;
-
-                // Set to the inner loop
-                if len == 8 {
-                    loop_num = 1;
-                    loop_c = 2;
-                } else if len == 1 {
-                    continue
-                }
-
-                // Right
-                if c < loop_c && r == Rotate::Right {
-                    let x = area[loop_num].pop().expect("Out of Range");
-                    area[loop_num].insert(0, x);
-
-                // Left
-                } else if c < loop_c && r == Rotate::Left {
-                    let x = *area[loop_num].get(0).expect("Out of Range");
-                    area[loop_num].remove(0);
-                    area[loop_num].push(x);
-                }
-            }
+        let hidden = -(grid.get_buffer() as isize);
+        if bits.iter().all(|pos| pos.0 < 0) || bits.iter().any(|pos| (pos.0 as isize) < hidden) {
+            return false
         }
+        for pos in bits {
+            grid.set_grid(pos, Cell::Filled{kind: self.kind, color: self.color})
+        }
+        true
+    }
 
-        let old_area = self.area.clone();
-        self.area = area;
+    // If the piece can be where it is
+    pub fn is_free(&self, grid: &Tetris) -> bool {
+        self.fits(NONE, grid)
+    }
 
-        match self.hit_detect(&Dir::None, tetris) {
-            Ok(()) => (),
-            Err(()) => self.area = old_area,
+    fn apply_dir(&mut self, dir: &Dir) {
+        self.position += dir.get();
+    }
+
+    pub fn get_bits_pos(&self) -> Vec<Pos> {
+        self.states[self.rotation].iter().map(|bit| self.get_pos() + *bit).collect()
+    }
+
+    // Rotate using the Super Rotation System
+    // If the piece doesn't fit, try to kick it into a spot that does
+    // Return true if the piece rotated
+    pub fn rotate(&mut self, r: Rotate, tetris: &Tetris) -> bool {
+        let from = self.rotation;
+        let to = match r {
+            Rotate::Right => (from + 1) % 4,
+            Rotate::Left => (from + 3) % 4,
+        };
+        let kicks = &self.kicks[kick_index(from, to)];
+        let old_pos = self.position;
+
+        self.rotation = to;
+        for (n, kick) in kicks.iter().enumerate() {
+            self.position = old_pos + *kick;
+            if self.hit_detect(&Dir::None, tetris).is_ok() {
+                self.kick = Some(n);
+                self.rotated = true;
+                return true
+            }
         }
 
+        self.rotation = from;
+        self.position = old_pos;
+        false
     }
 
     pub fn get_pos(&self) -> Pos {
@@ -319,12 +293,24 @@ impl Piece {
         self.alive = false
     }
 
-    pub fn r#move(&mut self, dir: Dir, grid: &Tetris) {
+    // Stop the piece where it is
+    // so it can be applied to the grid
+    pub fn lock(&mut self) {
+        self.kill()
+    }
+
+    // If the piece is resting on something
+    pub fn is_grounded(&self, grid: &Tetris) -> bool {
+        !self.fits(DOWN, grid)
+    }
+
+    // Return true if the piece moved
+    pub fn r#move(&mut self, dir: Dir, grid: &Tetris) -> bool {
         // If a hit is detected, don't move
         // Otherwise move
-        match self.hit_detect(&dir, &grid) {
-            Ok(_) => self.apply_dir(&dir),
-            Err(_) => (),
+        match self.hit_detect(&dir, grid) {
+            Ok(_) => {self.apply_dir(&dir); self.rotated = false; true},
+            Err(_) => false,
         }
     }
 }
\ No newline at end of file
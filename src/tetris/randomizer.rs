@@ -0,0 +1,159 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use crate::tetris::set::PieceSet;
+
+// Decides which piece of the set comes next
+// All randomness has to come from the rng given
+pub trait Randomizer {
+    fn next(&mut self, rng: &mut ChaCha8Rng, pieces: &PieceSet) -> usize;
+}
+
+// The randomizers to pick from when a game starts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Randomizers {
+    Uniform,
+    Bag,
+    History,
+}
+impl Randomizers {
+    pub fn from_name(name: &str) -> Option<Randomizers> {
+        match name {
+            "uniform" => Some(Randomizers::Uniform),
+            "bag" => Some(Randomizers::Bag),
+            "history" | "tgm" => Some(Randomizers::History),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            Randomizers::Uniform => "uniform",
+            Randomizers::Bag => "bag",
+            Randomizers::History => "history",
+        }
+    }
+
+    pub fn create(&self) -> Box<dyn Randomizer> {
+        match self {
+            Randomizers::Uniform => Box::new(Uniform),
+            Randomizers::Bag => Box::new(Bag::new()),
+            Randomizers::History => Box::new(History::new(6)),
+        }
+    }
+}
+
+// Every piece is as likely as any other, every time
+pub struct Uniform;
+impl Randomizer for Uniform {
+    fn next(&mut self, rng: &mut ChaCha8Rng, pieces: &PieceSet) -> usize {
+        rng.gen_range(0..pieces.len())
+    }
+}
+
+// Deal out one of every piece in a random order
+// then start again with a new bag
+pub struct Bag {
+    bag: Vec<usize>,
+}
+impl Bag {
+    pub fn new() -> Bag {
+        Bag{
+            bag: vec![],
+        }
+    }
+}
+impl Default for Bag {
+    fn default() -> Bag {
+        Bag::new()
+    }
+}
+impl Randomizer for Bag {
+    fn next(&mut self, rng: &mut ChaCha8Rng, pieces: &PieceSet) -> usize {
+        if self.bag.is_empty() {
+            self.bag = (0..pieces.len()).collect();
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().expect("Bag is empty")
+    }
+}
+
+// Remember the last four pieces and reroll
+// a few times if the new one is among them
+// The first piece is never one that can't come first
+pub struct History {
+    history: VecDeque<usize>,
+    rolls: usize,
+    first: bool,
+}
+impl History {
+    pub fn new(rolls: usize) -> History {
+        History{
+            history: VecDeque::new(),
+            rolls,
+            first: true,
+        }
+    }
+}
+impl Randomizer for History {
+    fn next(&mut self, rng: &mut ChaCha8Rng, pieces: &PieceSet) -> usize {
+        let mut piece = rng.gen_range(0..pieces.len());
+
+        if self.first {
+            self.first = false;
+
+            // Start with the history full of the pieces that can't come first
+            let unfair: Vec<usize> = (0..pieces.len()).filter(|n| !pieces.get(*n).is_first()).collect();
+            self.history = unfair.iter().cycle().take(4).copied().collect();
+
+            if unfair.len() < pieces.len() {
+                while !pieces.get(piece).is_first() {
+                    piece = rng.gen_range(0..pieces.len());
+                }
+            }
+        } else {
+            for _ in 1..self.rolls {
+                if !self.history.contains(&piece) {
+                    break
+                }
+                piece = rng.gen_range(0..pieces.len());
+            }
+        }
+
+        if self.history.len() >= 4 {
+            self.history.pop_front();
+        }
+        self.history.push_back(piece);
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn bags_deal_every_piece_once() {
+        let pieces = PieceSet::standard();
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut bag = Bag::new();
+        for _ in 0..3 {
+            let dealt: Vec<usize> = (0..pieces.len()).map(|_| bag.next(&mut rng, &pieces)).collect();
+            for piece in 0..pieces.len() {
+                assert_eq!(dealt.iter().filter(|x| **x == piece).count(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn history_never_starts_with_an_overhang() {
+        let pieces = PieceSet::standard();
+        for seed in 0..100 {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let first = History::new(6).next(&mut rng, &pieces);
+            assert!(pieces.get(first).is_first());
+        }
+    }
+}
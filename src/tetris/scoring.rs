@@ -0,0 +1,259 @@
+// If the piece spun into place
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+// What happened when a piece locked
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clear {
+    pub lines: u8,
+    pub spin: Spin,
+    pub perfect: bool, // The board is empty after the clear
+}
+impl Clear {
+    // Hard clears keep a back to back going
+    pub fn is_difficult(&self) -> bool {
+        self.lines >= 4 || (self.lines > 0 && self.spin != Spin::None)
+    }
+
+    // What to tell the player, None if there's nothing worth saying
+    pub fn get_message(&self) -> Option<String> {
+        let spin = match self.spin {
+            Spin::None => "",
+            Spin::Mini => "T-Spin Mini",
+            Spin::Full => "T-Spin",
+        };
+        let lines = match self.lines {
+            0 => "",
+            1 => "Single",
+            2 => "Double",
+            3 => "Triple",
+            _ if spin.is_empty() => "Tetris",
+            _ => "Quad",
+        };
+
+        let mut message = [spin, lines].iter().filter(|x| !x.is_empty()).copied().collect::<Vec<_>>().join(" ");
+        if message.is_empty() {
+            return None
+        }
+        if self.perfect {
+            message.push_str(" Perfect Clear");
+        }
+        Some(message)
+    }
+}
+
+// The ways points can be given out
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScoringRules {
+    Guideline, // Spins, combos, back to back and perfect clears
+    Classic, // Like the NES, only lines and soft drops
+}
+impl ScoringRules {
+    pub fn from_name(name: &str) -> Option<ScoringRules> {
+        match name {
+            "guideline" => Some(ScoringRules::Guideline),
+            "classic" | "nes" => Some(ScoringRules::Classic),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        match self {
+            ScoringRules::Guideline => "guideline",
+            ScoringRules::Classic => "classic",
+        }
+    }
+}
+
+// Keeps the score, and what it needs to remember
+// between pieces to give out bonuses
+pub struct Scoring {
+    rules: ScoringRules,
+    score: u64,
+    combo: Option<u32>, // How many clears in a row, None if the last piece didn't clear
+    back_to_back: bool, // If the last clear was a difficult one
+}
+impl Scoring {
+    pub fn new(rules: ScoringRules) -> Scoring {
+        Scoring{
+            rules,
+            score: 0,
+            combo: None,
+            back_to_back: false,
+        }
+    }
+
+    pub fn get_score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn get_combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    pub fn is_back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    fn add(&mut self, points: u64) -> u64 {
+        self.score = self.score.saturating_add(points);
+        points
+    }
+
+    // The piece was pushed down by the player
+    pub fn soft_drop(&mut self, rows: u32, level: u32) -> u64 {
+        self.add(rows as u64 * level as u64)
+    }
+
+    // The piece was dropped straight down
+    pub fn hard_drop(&mut self, rows: u32, level: u32) -> u64 {
+        match self.rules {
+            ScoringRules::Guideline => self.add(rows as u64 * 2 * level as u64),
+            ScoringRules::Classic => 0,
+        }
+    }
+
+    // A piece locked, return the points it was worth
+    pub fn lock(&mut self, clear: Clear, level: u32) -> u64 {
+        let points = match self.rules {
+            ScoringRules::Guideline => self.guideline(clear),
+            ScoringRules::Classic => classic(clear),
+        };
+        self.add(points * level as u64)
+    }
+
+    // Points for a clear before they are scaled by level
+    fn guideline(&mut self, clear: Clear) -> u64 {
+        let mut points = match (clear.spin, clear.lines) {
+            (Spin::None, 0) => 0,
+            (Spin::None, 1) => 100,
+            (Spin::None, 2) => 300,
+            (Spin::None, 3) => 500,
+            (Spin::Mini, 0) => 100,
+            (Spin::Mini, 1) => 200,
+            (Spin::Mini, _) => 400,
+            (Spin::Full, 0) => 400,
+            (Spin::Full, 1) => 800,
+            (Spin::Full, 2) => 1200,
+            (Spin::Full, _) => 1600,
+            (Spin::None, _) => 800,
+        };
+
+        if clear.lines == 0 {
+            self.combo = None;
+            return points
+        }
+
+        // Difficult clears in a row are worth half again
+        let back_to_back = self.back_to_back && clear.is_difficult();
+        if back_to_back {
+            points += points / 2;
+        }
+        self.back_to_back = clear.is_difficult();
+
+        let combo = match self.combo {
+            Some(combo) => combo + 1,
+            None => 0,
+        };
+        self.combo = Some(combo);
+        points += 50 * combo as u64;
+
+        if clear.perfect {
+            points += match clear.lines {
+                1 => 800,
+                2 => 1200,
+                3 => 1800,
+                _ if back_to_back => 3200,
+                _ => 2000,
+            };
+        }
+        points
+    }
+}
+
+// NES points for a clear before they are scaled by level
+// The NES counts levels from 0, so level 1 here is its level 0
+fn classic(clear: Clear) -> u64 {
+    match clear.lines {
+        0 => 0,
+        1 => 40,
+        2 => 100,
+        3 => 300,
+        _ => 1200,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(lines: u8, spin: Spin) -> Clear {
+        Clear{lines, spin, perfect: false}
+    }
+
+    #[test]
+    fn guideline_points_scale_by_level() {
+        let mut scoring = Scoring::new(ScoringRules::Guideline);
+        assert_eq!(scoring.lock(clear(1, Spin::None), 1), 100);
+        assert_eq!(scoring.lock(clear(0, Spin::None), 1), 0);
+        assert_eq!(scoring.lock(clear(4, Spin::None), 2), 1600);
+        assert_eq!(scoring.lock(clear(0, Spin::None), 1), 0);
+        assert_eq!(scoring.lock(clear(2, Spin::Full), 3), 1200 * 3 * 3 / 2);
+        assert_eq!(scoring.hard_drop(10, 2), 40);
+        assert_eq!(scoring.soft_drop(3, 2), 6);
+        assert_eq!(scoring.get_score(), 100 + 1600 + 5400 + 46);
+    }
+
+    #[test]
+    fn back_to_back_and_combo() {
+        let mut scoring = Scoring::new(ScoringRules::Guideline);
+        assert_eq!(scoring.lock(clear(4, Spin::None), 1), 800);
+        assert!(scoring.is_back_to_back());
+        assert_eq!(scoring.get_combo(), Some(0));
+        // Half again for the back to back, and 50 for the combo
+        assert_eq!(scoring.lock(clear(4, Spin::None), 1), 1200 + 50);
+        assert_eq!(scoring.get_combo(), Some(1));
+        // A single breaks the back to back but keeps the combo
+        assert_eq!(scoring.lock(clear(1, Spin::None), 1), 100 + 100);
+        assert!(!scoring.is_back_to_back());
+        // A spin without lines doesn't break it, but ends the combo
+        assert_eq!(scoring.lock(clear(0, Spin::Mini), 1), 100);
+        assert_eq!(scoring.get_combo(), None);
+        assert_eq!(scoring.lock(clear(1, Spin::Mini), 1), 200);
+        assert_eq!(scoring.lock(clear(1, Spin::Full), 1), 1200 + 50);
+    }
+
+    #[test]
+    fn perfect_clears() {
+        let mut scoring = Scoring::new(ScoringRules::Guideline);
+        let perfect = |lines| Clear{lines, spin: Spin::None, perfect: true};
+        assert_eq!(scoring.lock(perfect(1), 1), 100 + 800);
+        scoring.lock(clear(0, Spin::None), 1);
+        assert_eq!(scoring.lock(perfect(4), 1), 800 + 2000);
+        scoring.lock(clear(0, Spin::None), 1);
+        assert_eq!(scoring.lock(perfect(4), 1), 1200 + 3200);
+    }
+
+    #[test]
+    fn classic_has_no_bonuses() {
+        let mut scoring = Scoring::new(ScoringRules::Classic);
+        assert_eq!(scoring.lock(clear(4, Spin::None), 1), 1200);
+        assert_eq!(scoring.lock(clear(4, Spin::None), 1), 1200);
+        assert_eq!(scoring.lock(clear(1, Spin::Full), 3), 120);
+        assert_eq!(scoring.hard_drop(10, 1), 0);
+        assert_eq!(scoring.soft_drop(3, 2), 6);
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(clear(0, Spin::None).get_message(), None);
+        assert_eq!(clear(4, Spin::None).get_message().as_deref(), Some("Tetris"));
+        assert_eq!(clear(0, Spin::Full).get_message().as_deref(), Some("T-Spin"));
+        let perfect = Clear{lines: 2, spin: Spin::Mini, perfect: true};
+        assert_eq!(perfect.get_message().as_deref(), Some("T-Spin Mini Double Perfect Clear"));
+    }
+}
@@ -0,0 +1,378 @@
+use std::collections::HashMap;
+use std::fs;
+use crate::tetris::piece::{self, Pos};
+
+// A piece as it was written in a pieces file
+#[derive(Clone, Debug)]
+pub struct PieceDef {
+    name: String,
+    states: Vec<Vec<Pos>>, // Each rotation state, as bits from the top left
+    spawn: Pos, // Where the top left of the shape starts
+    kicks: Vec<Vec<Pos>>, // The kicks for each rotation, see piece::kick_index
+    color: Option<usize>, // None means a random color
+    first: bool, // If the piece can be dealt first
+    spin: bool, // If the piece can T-spin
+}
+impl PieceDef {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_states(&self) -> &Vec<Vec<Pos>> {
+        &self.states
+    }
+
+    pub fn get_spawn(&self) -> Pos {
+        self.spawn
+    }
+
+    pub fn get_kicks(&self) -> &Vec<Vec<Pos>> {
+        &self.kicks
+    }
+
+    pub fn get_color(&self) -> Option<usize> {
+        self.color
+    }
+
+    pub fn is_first(&self) -> bool {
+        self.first
+    }
+
+    pub fn can_spin(&self) -> bool {
+        self.spin
+    }
+}
+
+// All of the pieces a game can deal
+#[derive(Clone, Debug)]
+pub struct PieceSet {
+    pieces: Vec<PieceDef>,
+    hash: u64, // Of the text the pieces were read from, so replays can tell if it changed
+}
+impl PieceSet {
+    // The pieces the game comes with
+    pub fn standard() -> PieceSet {
+        PieceSet::parse(include_str!("../assets/pieces.txt")).expect("Invalid standard pieces")
+    }
+
+    pub fn load(path: &str) -> Result<PieceSet, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        PieceSet::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+
+    pub fn get(&self, n: usize) -> &PieceDef {
+        &self.pieces[n]
+    }
+
+    pub fn get_hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Read a pieces file, see assets/pieces.txt for how they look
+    pub fn parse(text: &str) -> Result<PieceSet, String> {
+        let mut tables: HashMap<String, Vec<Vec<Pos>>> = HashMap::new();
+        let mut raw: Vec<RawPiece> = vec![];
+        let mut table: Option<String> = None; // The kick table being read
+
+        for (n, line) in text.lines().enumerate() {
+            let error = |e: &str| format!("line {}: {}", n + 1, e);
+            let line = line.trim();
+            if line.starts_with(';') {
+                continue
+            }
+
+            // Rows of a shape
+            if !line.is_empty() && line.chars().all(|c| c == '.' || c == '#') {
+                let piece = raw.last_mut().ok_or_else(|| error("Shape outside of a piece"))?;
+                if piece.rows.first().is_some_and(|row| row.len() != line.len()) {
+                    return Err(error("Every row of a shape has to be as long"))
+                }
+                piece.rows.push(line.chars().map(|c| c == '#').collect());
+                continue
+            }
+
+            // Anything else ends the shape
+            if let Some(piece) = raw.last_mut() {
+                piece.end_shape();
+            }
+            if line.is_empty() {
+                continue
+            }
+
+            let mut words = line.split_whitespace();
+            let first = words.next().unwrap_or("");
+            let args: Vec<&str> = words.collect();
+
+            match (first, args.as_slice()) {
+                ("wallkicks", [name]) => {
+                    tables.insert(name.to_string(), vec![vec![]; 8]);
+                    table = Some(name.to_string());
+                },
+                ("piece", [name]) => {
+                    raw.push(RawPiece::new(name));
+                    table = None;
+                },
+                ("color", [color]) => {
+                    let piece = raw.last_mut().ok_or_else(|| error("color outside of a piece"))?;
+                    piece.color = match *color {
+                        "red" => Some(0),
+                        "green" => Some(1),
+                        "blue" => Some(2),
+                        "random" => None,
+                        _ => return Err(error("color has to be red, green, blue or random")),
+                    };
+                },
+                ("spawn", [row, column]) => {
+                    let piece = raw.last_mut().ok_or_else(|| error("spawn outside of a piece"))?;
+                    let row = row.parse().map_err(|_| error("spawn row isn't a number"))?;
+                    let column = column.parse().map_err(|_| error("spawn column isn't a number"))?;
+                    piece.spawn = Some(Pos(row, column));
+                },
+                ("kicks", [name]) => {
+                    let piece = raw.last_mut().ok_or_else(|| error("kicks outside of a piece"))?;
+                    piece.kicks = name.to_string();
+                },
+                ("first", [first]) => {
+                    let piece = raw.last_mut().ok_or_else(|| error("first outside of a piece"))?;
+                    piece.first = match *first {
+                        "yes" => true,
+                        "no" => false,
+                        _ => return Err(error("first has to be yes or no")),
+                    };
+                },
+                ("spin", [spin]) => {
+                    let piece = raw.last_mut().ok_or_else(|| error("spin outside of a piece"))?;
+                    piece.spin = match *spin {
+                        "yes" => true,
+                        "no" => false,
+                        _ => return Err(error("spin has to be yes or no")),
+                    };
+                },
+                (from, [to, offsets @ ..]) if table.is_some() => {
+                    let from = parse_rotation(from).ok_or_else(|| error("Unknown rotation"))?;
+                    let to = parse_rotation(to).ok_or_else(|| error("Unknown rotation"))?;
+                    if (from + 1) % 4 != to && (to + 1) % 4 != from {
+                        return Err(error("Rotations have to be a quarter turn"))
+                    }
+
+                    let mut kicks = vec![];
+                    for offset in offsets {
+                        let (x, y) = offset.split_once(',').ok_or_else(|| error("Offsets have to be x,y"))?;
+                        let x: i8 = x.parse().map_err(|_| error("Offset isn't a number"))?;
+                        let y: i8 = y.parse().map_err(|_| error("Offset isn't a number"))?;
+                        // Up is negative on the grid
+                        kicks.push(Pos(-y, x));
+                    }
+
+                    let name = table.as_ref().expect("No kick table");
+                    tables.get_mut(name).expect("No kick table")[piece::kick_index(from, to)] = kicks;
+                },
+                _ => return Err(error(&format!("Don't know what to do with \"{}\"", line))),
+            }
+        }
+
+        let mut pieces = vec![];
+        for mut piece in raw {
+            piece.end_shape();
+            pieces.push(piece.build(&tables)?);
+        }
+        if pieces.is_empty() {
+            return Err("There are no pieces".to_string())
+        }
+
+        Ok(PieceSet{
+            pieces,
+            hash: hash(text),
+        })
+    }
+}
+
+// A piece that is still being read
+struct RawPiece {
+    name: String,
+    shapes: Vec<Vec<Vec<bool>>>,
+    rows: Vec<Vec<bool>>, // The shape being read
+    spawn: Option<Pos>,
+    kicks: String,
+    color: Option<usize>,
+    first: bool,
+    spin: bool,
+}
+impl RawPiece {
+    fn new(name: &str) -> RawPiece {
+        RawPiece{
+            name: name.to_string(),
+            shapes: vec![],
+            rows: vec![],
+            spawn: None,
+            kicks: "none".to_string(),
+            color: None,
+            first: true,
+            spin: false,
+        }
+    }
+
+    fn end_shape(&mut self) {
+        if !self.rows.is_empty() {
+            self.shapes.push(std::mem::take(&mut self.rows));
+        }
+    }
+
+    fn build(self, tables: &HashMap<String, Vec<Vec<Pos>>>) -> Result<PieceDef, String> {
+        let error = |e: &str| format!("piece {}: {}", self.name, e);
+
+        let mut shapes = self.shapes.clone();
+        if shapes.is_empty() {
+            return Err(error("There is no shape"))
+        }
+        if shapes.len() > 4 {
+            return Err(error("There can only be 4 rotation states"))
+        }
+        if shapes.iter().any(|shape| shape.iter().flatten().all(|bit| !bit)) {
+            return Err(error("Every shape needs at least one #"))
+        }
+
+        // Make the missing states by turning the last one
+        // around the middle of its square
+        while shapes.len() < 4 {
+            let last = shapes.last().expect("No shapes");
+            let size = last.len();
+            if last.iter().any(|row| row.len() != size) {
+                return Err(error("Shapes that get turned have to be square"))
+            }
+            let turned = (0..size).map(|y| (0..size).map(|x| last[size - 1 - x][y]).collect()).collect();
+            shapes.push(turned);
+        }
+
+        if self.spin && shapes.iter().any(|shape| shape.len() != 3 || shape.iter().any(|row| row.len() != 3)) {
+            return Err(error("Only 3 by 3 shapes can spin"))
+        }
+
+        let kicks = match self.kicks.as_str() {
+            "none" => vec![vec![]; 8],
+            name => tables.get(name).ok_or_else(|| error(&format!("No kicks called {}", name)))?.clone(),
+        };
+        // Without any kicks, only try rotating in place
+        let kicks = kicks.into_iter().map(|x| if x.is_empty() {vec![Pos(0,0)]} else {x}).collect();
+
+        Ok(PieceDef{
+            states: shapes.iter().map(|shape| to_bits(shape)).collect(),
+            spawn: self.spawn.ok_or_else(|| error("There is no spawn"))?,
+            kicks,
+            color: self.color,
+            first: self.first,
+            spin: self.spin,
+            name: self.name,
+        })
+    }
+}
+
+// FNV-1a, it hashes the same text the same on every version of Rust
+fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// Turn 0, R, 2 or L into a rotation state
+fn parse_rotation(rotation: &str) -> Option<usize> {
+    match rotation {
+        "0" => Some(0),
+        "R" => Some(1),
+        "2" => Some(2),
+        "L" => Some(3),
+        _ => None,
+    }
+}
+
+// Turn a shape into where its bits are from the top left
+fn to_bits(shape: &[Vec<bool>]) -> Vec<Pos> {
+    let mut bits = vec![];
+    for (y, row) in shape.iter().enumerate() {
+        for (x, bit) in row.iter().enumerate() {
+            if *bit {
+                bits.push(Pos(y as i8, x as i8));
+            }
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::Tetris;
+    use crate::tetris::piece::{Piece, Rotate};
+
+    fn parse_error(text: &str) -> String {
+        PieceSet::parse(text).expect_err(text)
+    }
+
+    #[test]
+    fn standard_pieces() {
+        let set = PieceSet::standard();
+        assert_eq!(set.len(), 7);
+        let tee = set.get(3);
+        assert_eq!(tee.get_name(), "Tee");
+        assert!(tee.can_spin());
+        assert_eq!(tee.get_states().len(), 4);
+        assert_eq!(tee.get_spawn(), Pos(-2, 3));
+    }
+
+    #[test]
+    fn kicks_off_the_left_wall() {
+        let set = PieceSet::standard();
+        let tetris = Tetris::new(10, 20, 20);
+        let mut tee = Piece::new(3, set.get(3), 0);
+        assert!(tee.rotate(Rotate::Right, &tetris));
+        // Pointing right against the wall, the stem is in the second column
+        tee.set_pos(Pos(5, -1));
+        // Turning back doesn't fit in place, the second kick moves it right
+        assert!(tee.rotate(Rotate::Left, &tetris));
+        assert_eq!(tee.get_kick(), Some(1));
+        assert_eq!(tee.get_pos(), Pos(5, 0));
+    }
+
+    #[test]
+    fn kicks_the_straight_off_the_right_wall() {
+        let set = PieceSet::standard();
+        let tetris = Tetris::new(10, 20, 20);
+        let mut straight = Piece::new(6, set.get(6), 0);
+        assert!(straight.rotate(Rotate::Right, &tetris));
+        // Standing up in the last column
+        straight.set_pos(Pos(5, 7));
+        // R to 0 tries 0,0 then 2,0, both off the board, then -1,0
+        assert!(straight.rotate(Rotate::Left, &tetris));
+        assert_eq!(straight.get_kick(), Some(2));
+        assert_eq!(straight.get_pos(), Pos(5, 6));
+    }
+
+    #[test]
+    fn hashes_change_with_the_text() {
+        let text = include_str!("../assets/pieces.txt");
+        let standard = PieceSet::standard().get_hash();
+        assert_eq!(PieceSet::parse(text).unwrap().get_hash(), standard);
+        let changed = text.replacen("color random", "color red", 1);
+        assert_ne!(PieceSet::parse(&changed).unwrap().get_hash(), standard);
+    }
+
+    #[test]
+    fn bad_files_are_rejected() {
+        assert!(parse_error("").contains("no pieces"));
+        assert!(parse_error("##\n").contains("line 1"));
+        assert!(parse_error("piece A\n##\n#\n").contains("line 3"));
+        assert!(parse_error("piece A\ncolor pink\n##\n").contains("line 2"));
+        assert!(parse_error("piece A\nkicks srs\n##\n##\n").contains("No kicks called srs"));
+        assert!(parse_error("piece A\n..\n..\n").contains("at least one"));
+        assert!(parse_error("piece A\nspin yes\n##\n##\n").contains("3 by 3"));
+        assert!(parse_error("wallkicks a\n0 2 0,0\n").contains("quarter turn"));
+        assert!(parse_error("wallkicks a\n0 R 0\n").contains("x,y"));
+        assert!(parse_error("piece A\nwobble yes\n").contains("Don't know"));
+    }
+}